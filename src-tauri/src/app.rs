@@ -1,20 +1,20 @@
-use std::{io::Cursor, sync::Arc};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
 
 use anyhow::Error;
-use arrow::record_batch::RecordBatch;
-use duckdb::params;
-use futures::stream::{FuturesUnordered, StreamExt};
-use hotg_rune_compiler::{
-    asset_loader::{AssetLoader, DefaultAssetLoader},
-    BuildConfig, FeatureFlags,
-};
+use hotg_rune_compiler::asset_loader::{AssetLoader, DefaultAssetLoader};
 use serde::Serialize;
 use tauri::{Builder, CustomMenuItem, Manager, Menu, MenuItem, Submenu};
 
 use crate::{
+    blobs::{self, BlobStore},
+    compiler::{evict_idle_databases, Databases},
     legacy::{Cancelled, Running},
-    shared::Package,
-    wapm::fetch_packages,
+    proc_blocks::{run_sync, LocalAssetLoader, SyncState, DEFAULT_CONCURRENCY},
+    runtime::{evict_idle_sessions, ConfiguredLimits, RunningAnalyses, Sessions},
+    shared::ProcBlockSyncEvent,
     AppState,
 };
 
@@ -34,25 +34,29 @@ pub fn configure(state: AppState) -> Result<Builder<tauri::Wry>, Error> {
     let menu = Menu::new()
         .add_item(CustomMenuItem::new("hide", "Hide"))
         .add_submenu(submenu);
-    let assets: Arc<dyn AssetLoader + Send + Sync> =
+    let fallback: Arc<dyn AssetLoader + Send + Sync> =
         Arc::new(DefaultAssetLoader::default().cached());
+    let local_assets = Arc::new(LocalAssetLoader::new(state.meta_db_connection()?, fallback));
+    let assets: Arc<dyn AssetLoader + Send + Sync> = local_assets.clone();
+    let compiler = Databases::new(Arc::clone(&assets));
 
     let client = reqwest::Client::builder()
         .danger_accept_invalid_certs(true)
         .build()?;
 
-    let build_config = BuildConfig {
-        current_directory: std::env::current_dir()?,
-        features: FeatureFlags::stable(),
-    };
-
     let builder = Builder::default()
         .manage(state)
+        .manage(BlobStore::default())
+        .manage(Sessions::default())
+        .manage(ConfiguredLimits::default())
+        .manage(RunningAnalyses::default())
         .manage(Running::default())
         .manage(Cancelled::default())
+        .manage(SyncState::default())
         .manage(assets)
+        .manage(compiler)
+        .manage(local_assets)
         .manage(client)
-        .manage(build_config)
         .setup(|app: &mut tauri::App| {
             let handle = app.app_handle();
 
@@ -61,19 +65,52 @@ pub fn configure(state: AppState) -> Result<Builder<tauri::Wry>, Error> {
             // we perform the initialization code on a new task so the app doesn't freeze
             tracing::info!("Initializing...");
 
+            tauri::async_runtime::spawn(evict_idle_sessions(handle.clone()));
+            tauri::async_runtime::spawn(evict_idle_databases(handle.clone()));
             tauri::async_runtime::spawn(setup_weld(handle, main_window));
             Ok(())
         })
+        .register_uri_scheme_protocol(blobs::SCHEME, blobs::handle_request)
         .menu(menu)
         .on_menu_event(handle_menu_event)
         .on_window_event(handle_window_event)
         .invoke_handler(tauri::generate_handler![
+            crate::blobs::release_blob,
+            crate::bundles::export_rune,
+            crate::bundles::import_rune,
+            crate::compiler::check_runefile,
+            crate::compiler::clear_compilation_cache,
+            crate::datasets::check_tensor_compatibility,
             crate::datasets::create_dataset,
             crate::datasets::get_dataset_info,
             crate::datasets::list_datasets,
             crate::datasets::read_dataset_page,
             crate::logging::log_message,
+            crate::proc_blocks::asset_cache_stats,
+            crate::proc_blocks::cancel_proc_block_sync,
+            crate::proc_blocks::collect_proc_block_garbage,
+            crate::proc_blocks::install_proc_block_from_file,
+            crate::proc_blocks::proc_block_disk_usage,
+            crate::proc_blocks::proc_block_signature,
+            crate::proc_blocks::set_allow_network,
+            crate::proc_blocks::sync_proc_blocks,
+            crate::proc_blocks::uninstall_proc_block,
+            crate::projects::get_pipeline,
+            crate::projects::get_project_settings,
+            crate::projects::save_pipeline,
+            crate::projects::save_project_settings,
+            crate::runtime::cancel_analysis,
+            crate::runtime::delete_debug_run,
             crate::runtime::execute_analysis,
+            crate::runtime::get_debug_run,
+            crate::runtime::get_run_limits,
+            crate::runtime::inspect_rune,
+            crate::runtime::load_rune,
+            crate::runtime::read_captured_tensor,
+            crate::runtime::run_over_dataset,
+            crate::runtime::run_rune,
+            crate::runtime::set_run_limits,
+            crate::runtime::unload_rune,
             crate::sql::save_sql,
             crate::sql::validate_sql,
             crate::wapm::known_proc_blocks,
@@ -93,120 +130,42 @@ pub fn configure(state: AppState) -> Result<Builder<tauri::Wry>, Error> {
 async fn setup_weld(handle: tauri::AppHandle, main_window: tauri::Window) {
     let state: tauri::State<AppState> = handle.state();
     let client: tauri::State<reqwest::Client> = handle.state();
-    let home_dir = state.home_dir();
+    let sync: tauri::State<SyncState> = handle.state();
 
     emit_splashscreen_progress(&main_window, 10, "Fetching manifest...".to_string());
 
-    let conn = state.meta_db().await;
+    let progress = AtomicUsize::new(20);
 
-    let packages = match fetch_packages(client).await {
-        Ok(p) => p,
-        Err(e) => {
+    let result = run_sync(&state, &client, &sync, DEFAULT_CONCURRENCY, |event| {
+        match &event {
+            ProcBlockSyncEvent::Started { .. } => {
+                emit_splashscreen_progress(&main_window, 20, "Checking files".to_string());
+            }
+            ProcBlockSyncEvent::Finished { name, .. } => {
+                let progress = progress.fetch_add(1, Ordering::Relaxed) + 1;
+                emit_splashscreen_progress(
+                    &main_window,
+                    progress,
+                    format!("Fetched package {name}"),
+                );
+            }
+            _ => {}
+        }
+
+        // Let the rest of the UI see how the download is going, too
+        if let Err(e) = main_window.emit("proc_block_sync_progress", &event) {
             tracing::warn!(
                 error = &e as &dyn std::error::Error,
-                "Unable to fetch packages to check",
+                "Unable to emit a sync event",
             );
-            emit_splashscreen_progress(&main_window, 100, "Done".to_string());
-            return;
         }
-    };
-
-    emit_splashscreen_progress(&main_window, 20, "Checking files".to_string());
-
-    let packages_to_download: Vec<Package> = packages
-        .into_iter()
-        .filter(|package| {
-            let name: &str = package.name.as_str();
-            let version: &str = package.last_version.as_str();
-
-            let mut stmt = conn
-                .prepare("select version from proc_blocks where name = ? and version = ?")
-                .unwrap();
-            let found_pb = stmt.query_arrow(params![&name, &version]).unwrap();
-
-            let found_pb: Vec<RecordBatch> = found_pb.collect();
-
-            if found_pb.is_empty() {
-                // it doesn't exit
-                tracing::info!("No record of {}", package.name);
-                return true;
-            }
-
-            tracing::info!("Found new version of {}", package.name);
-            // it exists but has newer
-            found_pb.is_empty()
-        })
-        .collect();
-
-    tracing::info!(packages_to_download=?packages_to_download);
-
-    let mut futures = FuturesUnordered::new();
-
-    for package in packages_to_download {
-        let fut = async move {
-            let p = package.clone();
-            let response = reqwest::get(&package.public_url)
-                .await
-                .unwrap()
-                .bytes()
-                .await
-                .unwrap();
-
-            let proc_blocks_dir = home_dir
-                .join("proc_blocks")
-                .join(package.name)
-                .join(package.last_version);
-            match std::fs::create_dir_all(&proc_blocks_dir) {
-                Ok(_) => tracing::info!("Dir made"),
-                Err(e) => tracing::warn!("Dir err {:?}", e),
-            }
-            let file_name = proc_blocks_dir.join("pb.wasm");
-
-            tracing::info!("Writing to {:?}", &file_name.as_os_str());
-            match std::fs::File::create(file_name) {
-                Ok(mut file) => {
-                    let mut content = Cursor::new(response);
-
-                    std::io::copy(&mut content, &mut file).ok();
-                }
-                Err(e) => tracing::warn!("File can't be written {:?}", e),
-            }
-
-            p
-        };
-
-        futures.push(fut);
-    }
+    })
+    .await;
 
-    let mut progress = 20;
-    while let Some(package) = futures.next().await {
-        let proc_blocks_dir = home_dir
-            .join("proc_blocks")
-            .join(&package.name)
-            .join(&package.last_version);
-        let file_loc = proc_blocks_dir.join("pb.wasm");
-        progress += 1;
-
-        // Note: The body is a Result<Bytes, Error> here
-        tracing::info!("Writing {:?}", package);
-
-        let _found_pb = conn
-            .execute(
-                "INSERT INTO proc_blocks (name, version, publicUrl, description, fileLoc, createdAt) VALUES (?, ?, ?, ?, ?, now())",
-                params![
-                    &package.name,
-                    &package.last_version,
-                    &package.public_url,
-                    &package.description,
-                    file_loc.as_path().to_str()
-                ],
-            )
-            .unwrap();
-
-        emit_splashscreen_progress(
-            &main_window,
-            progress,
-            format!("Fetched package {}", package.name),
+    if let Err(e) = result {
+        tracing::warn!(
+            error = &*e as &dyn std::error::Error,
+            "Unable to sync the proc-blocks",
         );
     }
 
@@ -50,6 +50,18 @@ impl AppState {
         self.meta_conn.lock().await
     }
 
+    /// Open a second connection to `meta.db` which can be used from
+    /// synchronous code without going through [`AppState::meta_db()`].
+    pub fn meta_db_connection(&self) -> Result<Connection, Error> {
+        let conn = self
+            .meta_conn
+            .try_lock()
+            .context("The meta.db connection is already in use")?;
+
+        conn.try_clone()
+            .context("Unable to open another connection to meta.db")
+    }
+
     /**
      * try_get_db(): Blocking db connection
      */
@@ -66,5 +78,61 @@ pub fn prepare_meta_db(home_dir: &Path) -> Result<Connection, Error> {
     conn.execute(
         "CREATE TABLE IF NOT EXISTS proc_blocks(name VARCHAR, version VARCHAR, publicUrl VARCHAR, fileLoc VARCHAR, description VARCHAR, createdAt timestamp default now()) ", []
     ).map_err(|e| Error::msg(e.to_string()))?;
+    conn.execute(
+        "ALTER TABLE proc_blocks ADD COLUMN IF NOT EXISTS local BOOLEAN DEFAULT false",
+        [],
+    )
+    .map_err(|e| Error::msg(e.to_string()))?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS proc_block_signatures(name VARCHAR, version VARCHAR, signature VARCHAR, createdAt timestamp default now())", []
+    ).map_err(|e| Error::msg(e.to_string()))?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS compiled_runes(hash VARCHAR PRIMARY KEY, bytes BIGINT, lastUsed timestamp default now())", []
+    ).map_err(|e| Error::msg(e.to_string()))?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS projects(name VARCHAR PRIMARY KEY, directory VARCHAR, unstableFeatures BOOLEAN DEFAULT false)", []
+    ).map_err(|e| Error::msg(e.to_string()))?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS pipelines(project VARCHAR PRIMARY KEY, pipeline VARCHAR)",
+        [],
+    )
+    .map_err(|e| Error::msg(e.to_string()))?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS runefiles(project VARCHAR PRIMARY KEY, runefile VARCHAR)",
+        [],
+    )
+    .map_err(|e| Error::msg(e.to_string()))?;
+
+    for table in ["proc_blocks", "proc_block_signatures"] {
+        make_name_and_version_unique(&conn, table)?;
+    }
+
     Ok(conn)
 }
+
+/// Older versions of Weld would insert a new row every time a package was
+/// downloaded, so we need to remove any duplicates (keeping the most recent
+/// row) before `(name, version)` can be made unique.
+fn make_name_and_version_unique(conn: &Connection, table: &str) -> Result<(), Error> {
+    conn.execute(
+        &format!("DELETE FROM {table} WHERE rowid NOT IN (SELECT max(rowid) FROM {table} GROUP BY name, version)"),
+        [],
+    )
+    .map_err(|e| Error::msg(e.to_string()))
+    .with_context(|| format!("Unable to remove duplicate rows from the {table} table"))?;
+
+    conn.execute(
+        &format!(
+            "CREATE UNIQUE INDEX IF NOT EXISTS {table}_name_version ON {table} (name, version)"
+        ),
+        [],
+    )
+    .map_err(|e| Error::msg(e.to_string()))
+    .with_context(|| format!("Unable to add a uniqueness constraint to the {table} table"))?;
+
+    Ok(())
+}
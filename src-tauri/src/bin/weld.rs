@@ -13,6 +13,11 @@ use tracing_subscriber::{
 use weld::AppState;
 
 fn main() -> Result<(), Error> {
+    if std::env::args().nth(1).as_deref() == Some(weld::runtime::WORKER_FLAG) {
+        initialize_worker_logging();
+        return weld::runtime::run_worker();
+    }
+
     let project_dir = ProjectDirs::from("ai", "hotg", env!("CARGO_PKG_NAME"))
         .context("Unable to determine the project directory")?;
     let home = project_dir.data_local_dir();
@@ -64,3 +69,13 @@ fn initialize_logging(log_file: &Path) -> Result<(), Error> {
     tracing::info!(path = %log_file.display(), "Writing logs to disk");
     Ok(())
 }
+
+/// Workers talk to the main process over stdout, so they can only log to
+/// stderr.
+fn initialize_worker_logging() {
+    let stderr = tracing_subscriber::fmt::layer()
+        .with_writer(std::io::stderr)
+        .with_filter(EnvFilter::from_default_env());
+
+    Registry::default().with(stderr).init();
+}
@@ -0,0 +1,304 @@
+//! An in-memory store for large binary payloads (compiled Runes, Arrow IPC
+//! previews, tensors, etc.).
+//!
+//! Serializing a `Vec<u8>` through Tauri's IPC mechanism turns it into a JSON
+//! array of numbers, which gets really slow once you have more than a couple
+//! kilobytes. Instead, commands put their payload in the [`BlobStore`] and
+//! return its ID. The frontend can then fetch the raw bytes using the `weld://`
+//! custom protocol (see [`handle_request`]), or hand the ID to another command.
+//!
+//! The protocol accepts the following requests:
+//!
+//! - `GET /blobs/<id>` - read a blob
+//! - `POST /blobs` - upload a blob, returning its ID as plain text
+//! - `DELETE /blobs/<id>` - remove a blob from the store
+//!
+//! Only the app's own frontend may use the protocol, so requests from any
+//! other origin are rejected.
+
+use std::{
+    collections::HashMap,
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+
+use anyhow::Error;
+use bytes::Bytes;
+use tauri::{
+    http::{method::Method, Request, Response, ResponseBuilder},
+    AppHandle, Manager,
+};
+use uuid::Uuid;
+
+/// The URI scheme used to access the [`BlobStore`] from the frontend.
+pub const SCHEME: &str = "weld";
+
+/// Once the store grows past this many bytes, the least recently used blobs
+/// will be evicted.
+const MAX_STORE_SIZE: usize = 1024 * 1024 * 1024;
+
+/// The content type used for blobs uploaded by the frontend.
+const DEFAULT_CONTENT_TYPE: &str = "application/octet-stream";
+
+/// The origins the frontend is served from. Windows uses
+/// "https://tauri.localhost" while everyone else uses "tauri://localhost".
+const APP_ORIGINS: &[&str] = &["tauri://localhost", "https://tauri.localhost"];
+
+/// The `devPath` from `tauri.conf.json`, which debug builds are served from.
+const DEV_SERVER_ORIGIN: &str = "http://localhost:3000";
+
+/// Remove a blob from the store, returning `true` if it existed.
+#[tauri::command]
+#[tracing::instrument(skip(blobs))]
+pub fn release_blob(blobs: tauri::State<'_, BlobStore>, id: Uuid) -> bool {
+    blobs.remove(id)
+}
+
+#[derive(Debug)]
+pub struct BlobStore {
+    inner: Mutex<Inner>,
+    max_size: usize,
+}
+
+impl BlobStore {
+    pub fn with_max_size(max_size: usize) -> Self {
+        BlobStore {
+            inner: Mutex::default(),
+            max_size,
+        }
+    }
+
+    /// Add a blob to the store, returning its ID.
+    pub fn insert(&self, content_type: &str, bytes: impl Into<Bytes>) -> Uuid {
+        let bytes = bytes.into();
+        let id = Uuid::new_v4();
+        let mut inner = self.lock();
+
+        let last_used = inner.tick();
+        inner.total_size += bytes.len();
+        inner.blobs.insert(
+            id,
+            Blob {
+                content_type: content_type.to_string(),
+                bytes,
+                last_used,
+            },
+        );
+        inner.evict(self.max_size, id);
+
+        id
+    }
+
+    /// Get a blob's contents and content type.
+    pub fn get(&self, id: Uuid) -> Option<(String, Bytes)> {
+        let mut inner = self.lock();
+        let now = inner.tick();
+        let blob = inner.blobs.get_mut(&id)?;
+        blob.last_used = now;
+
+        Some((blob.content_type.clone(), blob.bytes.clone()))
+    }
+
+    /// Get a blob's contents, failing if it doesn't exist.
+    pub fn get_bytes(&self, id: Uuid) -> Result<Bytes, Error> {
+        self.get(id)
+            .map(|(_, bytes)| bytes)
+            .ok_or_else(|| Error::msg(format!("There is no blob with the ID, \"{id}\"")))
+    }
+
+    pub fn remove(&self, id: Uuid) -> bool {
+        let mut inner = self.lock();
+
+        match inner.blobs.remove(&id) {
+            Some(blob) => {
+                inner.total_size -= blob.bytes.len();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Default for BlobStore {
+    fn default() -> Self {
+        BlobStore::with_max_size(MAX_STORE_SIZE)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    blobs: HashMap<Uuid, Blob>,
+    total_size: usize,
+    /// A counter used to keep track of when each blob was last accessed.
+    clock: u64,
+}
+
+impl Inner {
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    /// Evict the least recently used blobs until the store fits within
+    /// `max_size`, making sure we don't evict the blob that was just added.
+    fn evict(&mut self, max_size: usize, keep: Uuid) {
+        while self.total_size > max_size {
+            let oldest = self
+                .blobs
+                .iter()
+                .filter(|(id, _)| **id != keep)
+                .min_by_key(|(_, blob)| blob.last_used)
+                .map(|(id, _)| *id);
+
+            let id = match oldest {
+                Some(id) => id,
+                None => break,
+            };
+
+            if let Some(blob) = self.blobs.remove(&id) {
+                tracing::debug!(%id, bytes = blob.bytes.len(), "Evicted a blob");
+                self.total_size -= blob.bytes.len();
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Blob {
+    content_type: String,
+    bytes: Bytes,
+    last_used: u64,
+}
+
+/// Handle a request made using the [`SCHEME`] protocol.
+pub fn handle_request(
+    app: &AppHandle,
+    request: &Request,
+) -> Result<Response, Box<dyn std::error::Error>> {
+    let blobs: tauri::State<BlobStore> = app.state();
+
+    // Note: Windows uses "https://weld.localhost/..." while everyone else
+    // uses "weld://localhost/..."
+    let path = request
+        .uri()
+        .split_once("://")
+        .map(|(_, rest)| rest)
+        .and_then(|rest| rest.find('/').map(|ix| &rest[ix..]))
+        .unwrap_or("/");
+    let path = path.split(|c| c == '?' || c == '#').next().unwrap_or(path);
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+
+    let method = request.method().clone();
+
+    let origin = request
+        .headers()
+        .get("Origin")
+        .and_then(|value| value.to_str().ok());
+    let respond = |status| respond_to(origin, status);
+
+    if let Some(origin) = origin {
+        if !is_app_origin(origin) {
+            tracing::warn!(%origin, uri = %request.uri(), "Rejected a blob request");
+            return Ok(respond_to(None, 403).body(Vec::new())?);
+        }
+    }
+
+    let response = match segments.as_slice() {
+        ["blobs"] if method == Method::POST || method == Method::PUT => {
+            let content_type = request
+                .headers()
+                .get("Content-Type")
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or(DEFAULT_CONTENT_TYPE);
+            let id = blobs.insert(content_type, request.body().clone());
+
+            respond(200)
+                .mimetype("text/plain")
+                .body(id.to_string().into_bytes())?
+        }
+        ["blobs", id] => match Uuid::parse_str(id) {
+            Err(_) => respond(400).body(b"Invalid blob ID".to_vec())?,
+            Ok(id) if method == Method::GET => match blobs.get(id) {
+                Some((content_type, bytes)) => {
+                    respond(200).mimetype(&content_type).body(bytes.to_vec())?
+                }
+                None => respond(404).body(Vec::new())?,
+            },
+            Ok(id) if method == Method::DELETE => {
+                let status = if blobs.remove(id) { 204 } else { 404 };
+                respond(status).body(Vec::new())?
+            }
+            Ok(_) if method == Method::OPTIONS => respond(204).body(Vec::new())?,
+            Ok(_) => respond(405).body(Vec::new())?,
+        },
+        ["blobs"] if method == Method::OPTIONS => respond(204).body(Vec::new())?,
+        _ => respond(404).body(Vec::new())?,
+    };
+
+    Ok(response)
+}
+
+fn is_app_origin(origin: &str) -> bool {
+    APP_ORIGINS.contains(&origin) || (cfg!(debug_assertions) && origin == DEV_SERVER_ORIGIN)
+}
+
+fn respond_to(origin: Option<&str>, status: u16) -> ResponseBuilder {
+    let builder = ResponseBuilder::new().status(status);
+
+    match origin {
+        Some(origin) => builder
+            .header("Access-Control-Allow-Origin", origin)
+            .header("Vary", "Origin")
+            .header(
+                "Access-Control-Allow-Methods",
+                "GET, POST, PUT, DELETE, OPTIONS",
+            )
+            .header("Access-Control-Allow-Headers", "Content-Type"),
+        None => builder,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_a_blob() {
+        let blobs = BlobStore::default();
+
+        let id = blobs.insert("text/plain", b"Hello, World!".to_vec());
+
+        let (content_type, bytes) = blobs.get(id).unwrap();
+        assert_eq!(content_type, "text/plain");
+        assert_eq!(bytes, &b"Hello, World!"[..]);
+        assert!(blobs.remove(id));
+        assert!(blobs.get(id).is_none());
+    }
+
+    #[test]
+    fn only_allow_the_app_origins() {
+        assert!(is_app_origin("tauri://localhost"));
+        assert!(is_app_origin("https://tauri.localhost"));
+        assert!(!is_app_origin("https://example.com"));
+        assert!(!is_app_origin("null"));
+        assert_eq!(is_app_origin(DEV_SERVER_ORIGIN), cfg!(debug_assertions));
+    }
+
+    #[test]
+    fn evict_the_least_recently_used_blob() {
+        let blobs = BlobStore::with_max_size(10);
+        let first = blobs.insert("", vec![0; 5]);
+        let second = blobs.insert("", vec![0; 5]);
+        // touch the first blob so the second one is older
+        blobs.get(first).unwrap();
+
+        let third = blobs.insert("", vec![0; 1]);
+
+        assert!(blobs.get(first).is_some());
+        assert!(blobs.get(second).is_none());
+        assert!(blobs.get(third).is_some());
+    }
+}
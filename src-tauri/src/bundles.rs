@@ -0,0 +1,342 @@
+//! Saving compiled Runes to disk so they can be shared with other people.
+//!
+//! A bundle is a `*.zune` archive with a JSON manifest next to it (e.g.
+//! `sentiment.zune` and `sentiment.json`) describing how the Rune was built
+//! and how a dataset should be fed into it.
+
+use std::{
+    fmt::{self, Display, Formatter},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Error};
+use duckdb::Connection;
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+use ts_rs::TS;
+use uuid::Uuid;
+
+use crate::{
+    compiler::ARCHIVE_CONTENT_TYPE,
+    proc_blocks::{find_file_location, resolve_identifier},
+    projects::load_settings,
+    shared::{ColumnMapping, NodeKind, Pipeline, Schema, SerializableError},
+    AppState, BlobStore,
+};
+
+/// The version of the manifest format written by [`export_rune`].
+const MANIFEST_VERSION: u32 = 1;
+
+/// Save a compiled Rune to disk, along with a manifest describing how it
+/// should be used.
+///
+/// The manifest is written next to the archive, with a `*.json` extension.
+#[tauri::command]
+#[tracing::instrument(skip(app, blobs, pipeline, column_mappings, input_schema), err)]
+pub async fn export_rune(
+    app: tauri::State<'_, AppState>,
+    blobs: tauri::State<'_, BlobStore>,
+    path: PathBuf,
+    rune: Uuid,
+    pipeline: Pipeline,
+    column_mappings: Vec<ColumnMapping>,
+    input_schema: Schema,
+) -> Result<RuneManifest, SerializableError> {
+    let rune = blobs.get_bytes(rune)?;
+    let proc_blocks = {
+        let conn = app.meta_db().await;
+        proc_block_versions(&conn, &pipeline)?
+    };
+
+    let manifest = RuneManifest {
+        manifest_version: MANIFEST_VERSION,
+        created_at: OffsetDateTime::now_utc()
+            .format(&Rfc3339)
+            .context("Unable to format the current time")?,
+        pipeline,
+        proc_blocks,
+        column_mappings,
+        input_schema,
+    };
+
+    let (archive_path, manifest_path) = bundle_paths(&path);
+
+    tokio::fs::write(&archive_path, &rune)
+        .await
+        .with_context(|| format!("Unable to write to \"{}\"", archive_path.display()))?;
+
+    let json = serde_json::to_vec_pretty(&manifest).context("Unable to serialize the manifest")?;
+    tokio::fs::write(&manifest_path, json)
+        .await
+        .with_context(|| format!("Unable to write to \"{}\"", manifest_path.display()))?;
+
+    tracing::info!(
+        archive = %archive_path.display(),
+        manifest = %manifest_path.display(),
+        "Exported a Rune",
+    );
+
+    Ok(manifest)
+}
+
+/// Import a bundle that was saved with [`export_rune`], copying it into the
+/// folder of the `project` with that name.
+///
+/// This fails with [`MissingProcBlocks`] if the pipeline uses proc-blocks
+/// that aren't in the local cache.
+#[tauri::command]
+#[tracing::instrument(skip(app, blobs), err)]
+pub async fn import_rune(
+    app: tauri::State<'_, AppState>,
+    blobs: tauri::State<'_, BlobStore>,
+    path: PathBuf,
+    project: String,
+) -> Result<ImportedRune, SerializableError<MissingProcBlocks>> {
+    let project = {
+        let conn = app.meta_db().await;
+        load_settings(&conn, app.home_dir(), &project)?
+    };
+    let (archive_path, manifest_path) = bundle_paths(&path);
+
+    let json = tokio::fs::read(&manifest_path)
+        .await
+        .with_context(|| format!("Unable to read \"{}\"", manifest_path.display()))?;
+    let manifest: RuneManifest = serde_json::from_slice(&json)
+        .with_context(|| format!("Unable to parse \"{}\"", manifest_path.display()))?;
+
+    if manifest.manifest_version > MANIFEST_VERSION {
+        return Err(Error::msg(format!(
+            "The bundle was created with a newer version of Weld (manifest version {})",
+            manifest.manifest_version
+        ))
+        .into());
+    }
+
+    let missing = {
+        let conn = app.meta_db().await;
+        missing_proc_blocks(&conn, &manifest.proc_blocks)?
+    };
+
+    if !missing.is_empty() {
+        return Err(Error::from(MissingProcBlocks { missing }).into());
+    }
+
+    let rune = tokio::fs::read(&archive_path)
+        .await
+        .with_context(|| format!("Unable to read \"{}\"", archive_path.display()))?;
+
+    let dir = project.directory.join("runes");
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .with_context(|| format!("Unable to create the \"{}\" directory", dir.display()))?;
+
+    let path = copy_into(&archive_path, &dir).await?;
+    copy_into(&manifest_path, &dir).await?;
+
+    Ok(ImportedRune {
+        path,
+        rune: blobs.insert(ARCHIVE_CONTENT_TYPE, rune),
+        manifest,
+    })
+}
+
+/// Everything needed to use a compiled Rune somewhere else.
+#[derive(Debug, Clone, PartialEq, Eq, TS, serde::Serialize, serde::Deserialize)]
+#[ts(export, export_to = "../src/backend/types/")]
+pub struct RuneManifest {
+    pub manifest_version: u32,
+    /// When the bundle was exported, as a RFC 3339 timestamp.
+    pub created_at: String,
+    pub pipeline: Pipeline,
+    /// The exact version of every proc-block used by the pipeline.
+    pub proc_blocks: Vec<ProcBlockVersion>,
+    pub column_mappings: Vec<ColumnMapping>,
+    /// The schema of the dataset the Rune was run against.
+    pub input_schema: Schema,
+}
+
+#[derive(
+    Debug, Clone, PartialEq, Eq, PartialOrd, Ord, TS, serde::Serialize, serde::Deserialize,
+)]
+#[ts(export, export_to = "../src/backend/types/")]
+pub struct ProcBlockVersion {
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, TS, serde::Serialize, serde::Deserialize)]
+#[ts(export, export_to = "../src/backend/types/")]
+pub struct ImportedRune {
+    /// Where the archive was copied to.
+    pub path: PathBuf,
+    /// The ID of the Rune archive in the [`BlobStore`].
+    pub rune: Uuid,
+    pub manifest: RuneManifest,
+}
+
+/// The bundle can't be imported until these proc-blocks are installed.
+#[derive(Debug, Clone, PartialEq, Eq, TS, serde::Serialize, serde::Deserialize)]
+#[ts(export, export_to = "../src/backend/types/")]
+pub struct MissingProcBlocks {
+    pub missing: Vec<ProcBlockVersion>,
+}
+
+impl Display for MissingProcBlocks {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "The bundle requires proc-blocks which aren't installed:")?;
+
+        for (i, ProcBlockVersion { name, version }) in self.missing.iter().enumerate() {
+            let separator = if i == 0 { " " } else { ", " };
+            write!(f, "{separator}{name}@{version}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for MissingProcBlocks {}
+
+/// Get the archive and manifest paths for a bundle.
+///
+/// The extensions are appended rather than replacing whatever is already
+/// there, so `model.v2` becomes `model.v2.zune` and `model.v2.json`. Either
+/// half of an existing bundle can also be used to refer to the whole thing.
+fn bundle_paths(path: &Path) -> (PathBuf, PathBuf) {
+    let base = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("zune" | "json") => path.with_extension(""),
+        _ => path.to_path_buf(),
+    };
+
+    (with_suffix(&base, ".zune"), with_suffix(&base, ".json"))
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_os_string();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// Copy a file into a directory, returning the new path.
+async fn copy_into(src: &Path, dir: &Path) -> Result<PathBuf, Error> {
+    let file_name = src
+        .file_name()
+        .with_context(|| format!("\"{}\" doesn't have a file name", src.display()))?;
+    let dest = dir.join(file_name);
+
+    if src != dest {
+        tokio::fs::copy(src, &dest).await.with_context(|| {
+            format!(
+                "Unable to copy \"{}\" to \"{}\"",
+                src.display(),
+                dest.display()
+            )
+        })?;
+    }
+
+    Ok(dest)
+}
+
+fn proc_block_versions(
+    conn: &Connection,
+    pipeline: &Pipeline,
+) -> Result<Vec<ProcBlockVersion>, Error> {
+    let mut versions = Vec::new();
+
+    for identifier in pipeline
+        .0
+        .values()
+        .filter(|node| node.ty == NodeKind::ProcBlock)
+        .map(|node| node.identifier.as_str())
+    {
+        let (name, version) = resolve_identifier(conn, identifier)?.with_context(|| {
+            format!("Unable to determine which version of \"{identifier}\" is being used")
+        })?;
+
+        versions.push(ProcBlockVersion { name, version });
+    }
+
+    versions.sort();
+    versions.dedup();
+
+    Ok(versions)
+}
+
+fn missing_proc_blocks(
+    conn: &Connection,
+    proc_blocks: &[ProcBlockVersion],
+) -> Result<Vec<ProcBlockVersion>, Error> {
+    let mut missing = Vec::new();
+
+    for pb in proc_blocks {
+        if find_file_location(conn, &pb.name, Some(&pb.version))?.is_none() {
+            missing.push(pb.clone());
+        }
+    }
+
+    Ok(missing)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use duckdb::params;
+
+    use super::*;
+
+    #[test]
+    fn append_the_bundle_extensions() {
+        assert_eq!(
+            bundle_paths(Path::new("exports/model.v2")),
+            (
+                PathBuf::from("exports/model.v2.zune"),
+                PathBuf::from("exports/model.v2.json")
+            )
+        );
+        assert_eq!(
+            bundle_paths(Path::new("exports/model.v2.zune")),
+            bundle_paths(Path::new("exports/model.v2")),
+        );
+    }
+
+    #[test]
+    fn manifest_round_trip() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE proc_blocks(name VARCHAR, version VARCHAR, fileLoc VARCHAR, createdAt timestamp default now())",
+            params![],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO proc_blocks(name, version, fileLoc) VALUES ('hotg-ai/argmax', '0.12.0', 'argmax.wasm')",
+            params![],
+        )
+        .unwrap();
+        let manifest = RuneManifest {
+            manifest_version: MANIFEST_VERSION,
+            created_at: "2022-06-01T12:00:00Z".to_string(),
+            pipeline: Pipeline(HashMap::new()),
+            proc_blocks: vec![
+                ProcBlockVersion {
+                    name: "hotg-ai/argmax".to_string(),
+                    version: "0.12.0".to_string(),
+                },
+                ProcBlockVersion {
+                    name: "hotg-ai/softmax".to_string(),
+                    version: "0.12.0".to_string(),
+                },
+            ],
+            column_mappings: Vec::new(),
+            input_schema: Schema {
+                fields: Vec::new(),
+                metadata: None,
+            },
+        };
+
+        let json = serde_json::to_vec_pretty(&manifest).unwrap();
+        let round_tripped: RuneManifest = serde_json::from_slice(&json).unwrap();
+        let missing = missing_proc_blocks(&conn, &round_tripped.proc_blocks).unwrap();
+
+        assert_eq!(round_tripped, manifest);
+        assert_eq!(missing, &manifest.proc_blocks[1..]);
+    }
+}
@@ -0,0 +1,395 @@
+//! A content-addressed cache of compiled Rune archives.
+//!
+//! Archives are saved to `$home_dir/compiled_runes/<hash>.rune`, where the
+//! hash covers everything that can change the compiler's output (the
+//! Runefile, the enabled feature flags, the exact version of each proc-block
+//! and the contents of any local models or resources). The `compiled_runes`
+//! table in `meta.db` keeps track of when each archive was last used so we
+//! can evict the least recently used ones.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Error};
+use duckdb::{params, Connection, OptionalExt};
+use hotg_rune_compiler::BuildConfig;
+use sha2::{Digest, Sha256};
+use ts_rs::TS;
+
+use crate::{
+    compiler::runefile::Runefile,
+    proc_blocks::{is_recorded, resolve_identifier},
+    shared::SerializableError,
+    AppState,
+};
+
+/// The most space compiled archives may take up before old ones get evicted.
+pub(crate) const MAX_CACHE_SIZE: u64 = 256 * 1024 * 1024;
+
+const CACHE_DIR: &str = "compiled_runes";
+
+/// Delete every compiled Rune archive from the cache.
+#[tauri::command]
+#[tracing::instrument(skip(app), err)]
+pub async fn clear_compilation_cache(
+    app: tauri::State<'_, AppState>,
+) -> Result<ClearedCache, SerializableError> {
+    let conn = app.meta_db().await;
+    let cleared = clear(&conn, app.home_dir())?;
+
+    tracing::info!(
+        removed = cleared.removed,
+        bytes_freed = cleared.bytes_freed,
+        "Cleared the compilation cache",
+    );
+
+    Ok(cleared)
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, TS)]
+#[ts(export, export_to = "../src/backend/types/")]
+pub struct ClearedCache {
+    /// The number of archives that were deleted.
+    pub removed: usize,
+    pub bytes_freed: u64,
+}
+
+/// Calculate the key a Runefile's compiled archive would be saved under.
+///
+/// Returns `None` when one of the proc-blocks can't be resolved to an exact
+/// version in the proc-block cache or a local file can't be read, because we
+/// have no way of knowing whether the compiler would give us the same thing
+/// next time (e.g. `hotg-ai/argmax@0.12` will mean something else once a new
+/// patch release is downloaded).
+pub(crate) fn cache_key(
+    conn: &Connection,
+    runefile: &str,
+    cfg: &BuildConfig,
+) -> Result<Option<String>, Error> {
+    let parsed = match Runefile::parse(runefile) {
+        Ok(r) => r,
+        // Let the compiler report the error
+        Err(_) => return Ok(None),
+    };
+
+    let mut hasher = Sha256::new();
+    hash_field(&mut hasher, env!("CARGO_PKG_VERSION"));
+    hash_field(&mut hasher, runefile);
+    // Relative paths to models are resolved against the project directory
+    hash_field(&mut hasher, &cfg.current_directory.display().to_string());
+    // FeatureFlags doesn't give us anything better to hash
+    hash_field(&mut hasher, &format!("{:?}", cfg.features));
+
+    for (stage, proc_block) in parsed
+        .pipeline
+        .iter()
+        .filter_map(|(name, stage)| Some((name, stage.proc_block.as_deref()?)))
+    {
+        match resolve_identifier(conn, proc_block)? {
+            Some((name, version)) if is_recorded(conn, &name, &version)? => {
+                hash_field(&mut hasher, stage);
+                hash_field(&mut hasher, &name);
+                hash_field(&mut hasher, &version);
+            }
+            _ => {
+                tracing::debug!(
+                    %stage,
+                    %proc_block,
+                    "Unable to resolve the proc-block to a version in the cache",
+                );
+                return Ok(None);
+            }
+        }
+    }
+
+    // Local files can be edited without touching the Runefile
+    for path in parsed.local_files() {
+        let full_path = cfg.current_directory.join(path);
+
+        match std::fs::read(&full_path) {
+            Ok(contents) => {
+                hash_field(&mut hasher, path);
+                hasher.update(Sha256::digest(&contents));
+            }
+            Err(e) => {
+                tracing::debug!(
+                    path = %full_path.display(),
+                    error = &e as &dyn std::error::Error,
+                    "Unable to read a local file referenced by the Runefile",
+                );
+                return Ok(None);
+            }
+        }
+    }
+
+    Ok(Some(format!("{:x}", hasher.finalize())))
+}
+
+/// Hash a length-prefixed string so adjacent fields can't run into each other.
+fn hash_field(hasher: &mut Sha256, value: &str) {
+    hasher.update((value.len() as u64).to_le_bytes());
+    hasher.update(value.as_bytes());
+}
+
+/// Look up a previously compiled archive, marking it as recently used.
+pub(crate) fn lookup(
+    conn: &Connection,
+    home_dir: &Path,
+    key: &str,
+) -> Result<Option<Vec<u8>>, Error> {
+    let known: Option<String> = conn
+        .query_row(
+            "SELECT hash FROM compiled_runes WHERE hash = ?",
+            params![key],
+            |row| row.get(0),
+        )
+        .optional()
+        .context("Unable to query the compilation cache")?;
+
+    if known.is_none() {
+        return Ok(None);
+    }
+
+    let path = archive_path(home_dir, key);
+
+    let archive = match std::fs::read(&path) {
+        Ok(archive) => archive,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            // Someone deleted the file behind our back
+            conn.execute("DELETE FROM compiled_runes WHERE hash = ?", params![key])
+                .context("Unable to update the compilation cache")?;
+            return Ok(None);
+        }
+        Err(e) => {
+            return Err(Error::new(e).context(format!("Unable to read \"{}\"", path.display())));
+        }
+    };
+
+    conn.execute(
+        "UPDATE compiled_runes SET lastUsed = now() WHERE hash = ?",
+        params![key],
+    )
+    .context("Unable to update the compilation cache")?;
+
+    Ok(Some(archive))
+}
+
+/// Save a compiled archive, evicting the least recently used archives if the
+/// cache would grow past `max_size` bytes.
+pub(crate) fn store(
+    conn: &Connection,
+    home_dir: &Path,
+    key: &str,
+    archive: &[u8],
+    max_size: u64,
+) -> Result<(), Error> {
+    let dir = home_dir.join(CACHE_DIR);
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("Unable to create the \"{}\" directory", dir.display()))?;
+
+    let path = archive_path(home_dir, key);
+    std::fs::write(&path, archive)
+        .with_context(|| format!("Unable to write to \"{}\"", path.display()))?;
+
+    conn.execute("DELETE FROM compiled_runes WHERE hash = ?", params![key])
+        .context("Unable to update the compilation cache")?;
+    conn.execute(
+        "INSERT INTO compiled_runes(hash, bytes, lastUsed) VALUES (?, ?, now())",
+        params![key, archive.len() as i64],
+    )
+    .context("Unable to update the compilation cache")?;
+
+    evict(conn, home_dir, max_size)
+}
+
+fn evict(conn: &Connection, home_dir: &Path, max_size: u64) -> Result<(), Error> {
+    let mut total = 0;
+
+    for (key, bytes) in entries(conn, "ORDER BY lastUsed DESC")? {
+        total += bytes;
+
+        if total > max_size {
+            tracing::debug!(%key, bytes, "Evicting a compiled Rune");
+            remove(conn, home_dir, &key)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn clear(conn: &Connection, home_dir: &Path) -> Result<ClearedCache, Error> {
+    let mut cleared = ClearedCache::default();
+
+    for (key, bytes) in entries(conn, "")? {
+        remove(conn, home_dir, &key)?;
+        cleared.removed += 1;
+        cleared.bytes_freed += bytes;
+    }
+
+    Ok(cleared)
+}
+
+fn entries(conn: &Connection, order_by: &str) -> Result<Vec<(String, u64)>, Error> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT hash, bytes FROM compiled_runes {order_by}"
+        ))
+        .context("Unable to query the compilation cache")?;
+
+    let rows = stmt
+        .query_map(params![], |row| {
+            Ok((row.get(0)?, row.get::<_, i64>(1)?.max(0) as u64))
+        })
+        .context("Unable to query the compilation cache")?;
+
+    rows.collect::<Result<_, _>>()
+        .context("Unable to read the compilation cache")
+}
+
+fn remove(conn: &Connection, home_dir: &Path, key: &str) -> Result<(), Error> {
+    let path = archive_path(home_dir, key);
+
+    match std::fs::remove_file(&path) {
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => {
+            return Err(Error::new(e).context(format!("Unable to delete \"{}\"", path.display())));
+        }
+    }
+
+    conn.execute("DELETE FROM compiled_runes WHERE hash = ?", params![key])
+        .context("Unable to update the compilation cache")?;
+
+    Ok(())
+}
+
+fn archive_path(home_dir: &Path, key: &str) -> PathBuf {
+    home_dir.join(CACHE_DIR).join(key).with_extension("rune")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use hotg_rune_compiler::FeatureFlags;
+
+    use super::*;
+
+    fn setup() -> (Connection, PathBuf) {
+        let home_dir = std::env::temp_dir().join(format!("weld-cache-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&home_dir).unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE compiled_runes(hash VARCHAR PRIMARY KEY, bytes BIGINT, lastUsed timestamp default now())",
+            params![],
+        )
+        .unwrap();
+        conn.execute(
+            "CREATE TABLE proc_blocks(name VARCHAR, version VARCHAR, publicUrl VARCHAR, fileLoc VARCHAR, createdAt timestamp default now())",
+            params![],
+        )
+        .unwrap();
+
+        (conn, home_dir)
+    }
+
+    fn build_config(current_directory: &Path) -> BuildConfig {
+        BuildConfig {
+            current_directory: current_directory.to_path_buf(),
+            features: FeatureFlags::stable(),
+        }
+    }
+
+    #[test]
+    fn key_changes_when_a_local_model_changes() {
+        let (conn, home_dir) = setup();
+        let cfg = build_config(&home_dir);
+        let runefile = "pipeline:\n  model:\n    model: ./model.tflite\n";
+        let model = home_dir.join("model.tflite");
+
+        assert_eq!(cache_key(&conn, runefile, &cfg).unwrap(), None);
+
+        std::fs::write(&model, b"first").unwrap();
+        let first = cache_key(&conn, runefile, &cfg).unwrap().unwrap();
+        assert_eq!(cache_key(&conn, runefile, &cfg).unwrap().unwrap(), first);
+
+        std::fs::write(&model, b"second").unwrap();
+        let second = cache_key(&conn, runefile, &cfg).unwrap().unwrap();
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn partial_versions_are_only_cached_once_resolved() {
+        let (conn, home_dir) = setup();
+        let cfg = build_config(&home_dir);
+        let runefile = "pipeline:\n  argmax:\n    proc-block: hotg-ai/argmax@0.12\n";
+
+        assert_eq!(cache_key(&conn, runefile, &cfg).unwrap(), None);
+
+        conn.execute(
+            "INSERT INTO proc_blocks(name, version) VALUES ('hotg-ai/argmax', '0.12.0')",
+            params![],
+        )
+        .unwrap();
+        let first = cache_key(&conn, runefile, &cfg).unwrap().unwrap();
+
+        conn.execute(
+            "INSERT INTO proc_blocks(name, version) VALUES ('hotg-ai/argmax', '0.12.1')",
+            params![],
+        )
+        .unwrap();
+        let second = cache_key(&conn, runefile, &cfg).unwrap().unwrap();
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn look_up_a_stored_archive() {
+        let (conn, home_dir) = setup();
+
+        assert_eq!(lookup(&conn, &home_dir, "abcd").unwrap(), None);
+
+        store(&conn, &home_dir, "abcd", b"rune", MAX_CACHE_SIZE).unwrap();
+
+        assert_eq!(
+            lookup(&conn, &home_dir, "abcd").unwrap(),
+            Some(b"rune".to_vec())
+        );
+    }
+
+    #[test]
+    fn evict_the_least_recently_used_archive() {
+        let (conn, home_dir) = setup();
+
+        store(&conn, &home_dir, "first", &[0; 10], 20).unwrap();
+        std::thread::sleep(Duration::from_millis(10));
+        store(&conn, &home_dir, "second", &[0; 10], 20).unwrap();
+        std::thread::sleep(Duration::from_millis(10));
+        // Using the first archive means the second one is now the oldest
+        lookup(&conn, &home_dir, "first").unwrap().unwrap();
+        std::thread::sleep(Duration::from_millis(10));
+        store(&conn, &home_dir, "third", &[0; 10], 20).unwrap();
+
+        assert!(lookup(&conn, &home_dir, "first").unwrap().is_some());
+        assert!(lookup(&conn, &home_dir, "second").unwrap().is_none());
+        assert!(!archive_path(&home_dir, "second").exists());
+        assert!(lookup(&conn, &home_dir, "third").unwrap().is_some());
+    }
+
+    #[test]
+    fn clear_everything() {
+        let (conn, home_dir) = setup();
+        store(&conn, &home_dir, "first", &[0; 10], MAX_CACHE_SIZE).unwrap();
+        store(&conn, &home_dir, "second", &[0; 5], MAX_CACHE_SIZE).unwrap();
+
+        let cleared = clear(&conn, &home_dir).unwrap();
+
+        assert_eq!(
+            cleared,
+            ClearedCache {
+                removed: 2,
+                bytes_freed: 15
+            }
+        );
+        assert!(entries(&conn, "").unwrap().is_empty());
+        assert!(!archive_path(&home_dir, "first").exists());
+    }
+}
@@ -0,0 +1,402 @@
+//! Checks that are run on a Runefile before it gets passed to the compiler.
+
+use std::collections::{BTreeMap, HashSet};
+
+use crate::{
+    compiler::{
+        runefile::{find_key, output_reference, parse_element_type, Runefile, Stage, TensorType},
+        Diagnostic, Severity,
+    },
+    proc_blocks::{load_signature, resolve_identifier},
+    shared::{Dimensions, ProcBlockSignature, TensorSignature},
+    AppState,
+};
+
+/// The signature for each proc-block stage, keyed by stage name.
+pub(crate) type Signatures = BTreeMap<String, ProcBlockSignature>;
+
+/// Look up the signature for every proc-block stage that we know about.
+///
+/// Proc-blocks which aren't in the local cache can't be checked, so they get
+/// a note instead.
+pub(crate) async fn stage_signatures(
+    app: &AppState,
+    src: &str,
+    runefile: &Runefile,
+) -> (Signatures, Vec<Diagnostic>) {
+    let mut signatures = Signatures::new();
+    let mut diags = Vec::new();
+
+    for (stage, proc_block) in runefile
+        .pipeline
+        .iter()
+        .filter_map(|(name, stage)| Some((name, stage.proc_block.as_deref()?)))
+    {
+        let resolved = {
+            let conn = app.meta_db().await;
+            resolve_identifier(&conn, proc_block)
+        };
+
+        let result = match resolved {
+            Ok(Some((name, version))) => load_signature(app, name, version).await,
+            Ok(None) => {
+                diags.push(
+                    Diagnostic::note(
+                        "proc-block-not-installed",
+                        format!("\"{proc_block}\" isn't in the proc-block cache, so it can't be checked"),
+                    )
+                    .with_stage(stage.as_str()),
+                );
+                continue;
+            }
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Ok(signature) => {
+                signatures.insert(stage.clone(), signature);
+            }
+            Err(e) => {
+                tracing::warn!(
+                    error = &*e as &dyn std::error::Error,
+                    %stage,
+                    %proc_block,
+                    "Unable to determine the proc-block's signature",
+                );
+                diags.push(
+                    Diagnostic::note(
+                        "signature-unavailable",
+                        format!("Unable to determine the signature for \"{proc_block}\": {e}"),
+                    )
+                    .with_stage(stage.as_str()),
+                );
+            }
+        }
+    }
+
+    let diags = diags.into_iter().map(|d| d.locate(src)).collect();
+
+    (signatures, diags)
+}
+
+/// Look for mistakes in a Runefile which would otherwise only be found (with
+/// a much less helpful error message) during codegen.
+pub(crate) fn check(src: &str, runefile: &Runefile, signatures: &Signatures) -> Vec<Diagnostic> {
+    let mut diags = Vec::new();
+
+    for (name, stage) in &runefile.pipeline {
+        let signature = signatures.get(name);
+
+        check_inputs(runefile, name, stage, signature, &mut diags);
+
+        if let Some(signature) = signature {
+            check_arguments(name, stage, signature, &mut diags);
+        }
+    }
+
+    check_resources(src, runefile, &mut diags);
+
+    diags.into_iter().map(|d| d.locate(src)).collect()
+}
+
+fn check_inputs(
+    runefile: &Runefile,
+    name: &str,
+    stage: &Stage,
+    signature: Option<&ProcBlockSignature>,
+    diags: &mut Vec<Diagnostic>,
+) {
+    if let Some(signature) = signature {
+        if !signature.inputs.is_empty() && signature.inputs.len() != stage.inputs.len() {
+            diags.push(
+                Diagnostic::error(
+                    "wrong-number-of-inputs",
+                    format!(
+                        "The \"{name}\" stage should have {} inputs, but it has {}",
+                        signature.inputs.len(),
+                        stage.inputs.len(),
+                    ),
+                )
+                .with_stage(name),
+            );
+        }
+    }
+
+    // The signature was calculated using the proc-block's default arguments,
+    // so it may not apply when the stage sets its own.
+    let severity = if stage.args.is_empty() {
+        Severity::Error
+    } else {
+        Severity::Warning
+    };
+
+    for (i, input) in stage.inputs.iter().enumerate() {
+        let (referenced, index) = output_reference(input);
+
+        let upstream = match runefile.pipeline.get(referenced) {
+            Some(s) => s,
+            None => {
+                diags.push(
+                    Diagnostic::error(
+                        "unknown-stage",
+                        format!(
+                            "The \"{name}\" stage refers to an unknown stage, \"{referenced}\""
+                        ),
+                    )
+                    .with_stage(name)
+                    .with_tensor(input.as_str()),
+                );
+                continue;
+            }
+        };
+
+        if upstream.outputs.is_empty() {
+            // Nothing to check against
+            continue;
+        }
+
+        let output = match upstream.outputs.get(index) {
+            Some(o) => o,
+            None => {
+                diags.push(
+                    Diagnostic::error(
+                        "unknown-output",
+                        format!(
+                            "The \"{name}\" stage uses output {index} from \"{referenced}\", but it only has {} outputs",
+                            upstream.outputs.len(),
+                        ),
+                    )
+                    .with_stage(name)
+                    .with_tensor(input.as_str()),
+                );
+                continue;
+            }
+        };
+
+        if let Some(expected) = signature.and_then(|s| s.inputs.get(i)) {
+            if let Some(problem) = mismatch(output, expected) {
+                diags.push(
+                    Diagnostic {
+                        severity,
+                        ..Diagnostic::error(
+                            "tensor-mismatch",
+                            format!(
+                                "The \"{}\" input to \"{name}\" {problem}, but \"{input}\" is {}",
+                                expected.name,
+                                describe(output),
+                            ),
+                        )
+                    }
+                    .with_stage(name)
+                    .with_tensor(expected.name.as_str()),
+                );
+            }
+        }
+    }
+}
+
+/// Explain why a tensor doesn't match what a proc-block expects.
+fn mismatch(actual: &TensorType, expected: &TensorSignature) -> Option<String> {
+    if let Some(element_type) = parse_element_type(&actual.element_type) {
+        if !expected.element_types.is_empty() && !expected.element_types.contains(&element_type) {
+            let names: Vec<_> = expected
+                .element_types
+                .iter()
+                .map(|e| format!("{e:?}").to_lowercase())
+                .collect();
+            return Some(format!("should be one of {}", names.join(", ")));
+        }
+    }
+
+    let dimensions = Dimensions::with_wildcards(&actual.dimensions);
+    if let Err(reason) = expected.dimensions.check(&dimensions) {
+        return Some(format!(
+            "should have dimensions {} ({reason})",
+            expected.dimensions
+        ));
+    }
+
+    None
+}
+
+fn describe(tensor: &TensorType) -> String {
+    format!(
+        "{} with dimensions {}",
+        tensor.element_type,
+        Dimensions::with_wildcards(&tensor.dimensions)
+    )
+}
+
+fn check_arguments(
+    name: &str,
+    stage: &Stage,
+    signature: &ProcBlockSignature,
+    diags: &mut Vec<Diagnostic>,
+) {
+    for arg in &signature.arguments {
+        if arg.default_value.is_none() && !stage.args.contains_key(&arg.name) {
+            diags.push(
+                Diagnostic::error(
+                    "missing-argument",
+                    format!(
+                        "The \"{name}\" stage is missing the \"{}\" argument",
+                        arg.name
+                    ),
+                )
+                .with_stage(name)
+                .with_argument(arg.name.as_str()),
+            );
+        }
+    }
+
+    for arg in stage.args.keys() {
+        if !signature.arguments.iter().any(|a| &a.name == arg) {
+            diags.push(
+                Diagnostic::warning(
+                    "unknown-argument",
+                    format!(
+                        "\"{}\" doesn't accept an argument called \"{arg}\"",
+                        signature.name
+                    ),
+                )
+                .with_stage(name)
+                .with_argument(arg.as_str()),
+            );
+        }
+    }
+}
+
+fn check_resources(src: &str, runefile: &Runefile, diags: &mut Vec<Diagnostic>) {
+    let mut used = HashSet::new();
+
+    for (name, stage) in &runefile.pipeline {
+        let args = stage
+            .args
+            .iter()
+            .filter_map(|(arg, value)| Some((Some(arg), value.as_str()?)));
+        let model = stage.model.as_deref().map(|m| (None, m));
+
+        for (arg, value) in args.chain(model) {
+            let resource = match value.strip_prefix('$') {
+                Some(r) => r,
+                None => continue,
+            };
+
+            if runefile.resources.contains_key(resource) {
+                used.insert(resource);
+                continue;
+            }
+
+            let mut diag = Diagnostic::error(
+                "unknown-resource",
+                format!("The \"{name}\" stage refers to an unknown resource, \"{resource}\""),
+            )
+            .with_stage(name.as_str());
+            if let Some(arg) = arg {
+                diag = diag.with_argument(arg.as_str());
+            }
+            diags.push(diag);
+        }
+    }
+
+    for resource in runefile.resources.keys() {
+        if !used.contains(resource.as_str()) {
+            diags.push(Diagnostic {
+                span: find_key(src, &["resources", resource.as_str()]),
+                ..Diagnostic::warning(
+                    "unused-resource",
+                    format!("The \"{resource}\" resource is never used"),
+                )
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shared::{ArgumentInfo, ElementType};
+
+    use super::*;
+
+    const RUNEFILE: &str = r#"version: 1
+image: runicos/base
+pipeline:
+  input:
+    capability: RAW
+    outputs:
+      - type: f64
+        dimensions: [1, 4]
+  argmax:
+    proc-block: "wapm:///hotg-ai/argmax?version=0.12.0"
+    inputs:
+      - input
+      - missing
+resources:
+  WORDLIST:
+    path: ./wordlist.txt
+"#;
+
+    fn argmax() -> ProcBlockSignature {
+        ProcBlockSignature {
+            name: "hotg-ai/argmax".to_string(),
+            version: "0.12.0".to_string(),
+            description: None,
+            arguments: vec![ArgumentInfo {
+                name: "element_type".to_string(),
+                description: None,
+                ty: crate::shared::ArgumentType::String,
+                default_value: None,
+            }],
+            inputs: vec![TensorSignature {
+                name: "input".to_string(),
+                description: None,
+                element_types: vec![ElementType::F32],
+                dimensions: Dimensions::Fixed(vec![None]),
+            }],
+            outputs: Vec::new(),
+        }
+    }
+
+    fn codes(diags: &[Diagnostic]) -> Vec<&str> {
+        diags.iter().map(|d| d.code.as_str()).collect()
+    }
+
+    #[test]
+    fn find_problems_without_signatures() {
+        let runefile = Runefile::parse(RUNEFILE).unwrap();
+
+        let diags = check(RUNEFILE, &runefile, &Signatures::new());
+
+        assert_eq!(codes(&diags), ["unknown-stage", "unused-resource"]);
+        assert_eq!(diags[0].stage.as_deref(), Some("argmax"));
+        assert_eq!(diags[1].severity, Severity::Warning);
+        assert_eq!(diags[1].span.unwrap().line, 15);
+    }
+
+    #[test]
+    fn use_signatures_to_check_arguments_and_tensors() {
+        let runefile = Runefile::parse(RUNEFILE).unwrap();
+        let signatures = Signatures::from([("argmax".to_string(), argmax())]);
+
+        let diags = check(RUNEFILE, &runefile, &signatures);
+
+        assert_eq!(
+            codes(&diags),
+            [
+                "wrong-number-of-inputs",
+                "tensor-mismatch",
+                "unknown-stage",
+                "missing-argument",
+                "unused-resource"
+            ]
+        );
+        assert_eq!(diags[3].argument.as_deref(), Some("element_type"));
+    }
+
+    #[test]
+    fn parse_output_references() {
+        assert_eq!(output_reference("fft"), ("fft", 0));
+        assert_eq!(output_reference("fft.2"), ("fft", 2));
+        assert_eq!(output_reference("v1.5x"), ("v1.5x", 0));
+    }
+}
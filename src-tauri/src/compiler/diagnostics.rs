@@ -0,0 +1,375 @@
+use std::fmt::{self, Display, Formatter};
+
+use ts_rs::TS;
+use uriparse::URI;
+
+use crate::{
+    compiler::runefile::{find_key, Runefile},
+    proc_blocks::{package_reference, parse_package},
+};
+
+/// Extra context for why a Runefile couldn't be compiled.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, TS)]
+#[ts(export, export_to = "../src/backend/types/")]
+pub struct CompileFailed {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Display for CompileFailed {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.diagnostics.as_slice() {
+            [] => write!(f, "Compilation failed"),
+            [single] => write!(f, "{}", single.message),
+            [first, rest @ ..] => write!(f, "{} (and {} more)", first.message, rest.len()),
+        }
+    }
+}
+
+impl std::error::Error for CompileFailed {}
+
+/// A message about something in the Runefile, typically pointing at the
+/// pipeline stage it is about.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, TS)]
+#[ts(export, export_to = "../src/backend/types/")]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// A short, machine-readable identifier for this kind of diagnostic.
+    pub code: String,
+    pub message: String,
+    /// The name of the pipeline stage this diagnostic is about.
+    pub stage: Option<String>,
+    pub argument: Option<String>,
+    pub tensor: Option<String>,
+    /// Where in the Runefile the problem was found.
+    pub span: Option<Span>,
+}
+
+impl Diagnostic {
+    pub fn error(code: impl Into<String>, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            code: code.into(),
+            message: message.into(),
+            stage: None,
+            argument: None,
+            tensor: None,
+            span: None,
+        }
+    }
+
+    pub fn warning(code: impl Into<String>, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            ..Diagnostic::error(code, message)
+        }
+    }
+
+    pub fn note(code: impl Into<String>, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity: Severity::Note,
+            ..Diagnostic::error(code, message)
+        }
+    }
+
+    /// Point this diagnostic at a pipeline stage.
+    pub fn with_stage(mut self, stage: impl Into<String>) -> Self {
+        self.stage = Some(stage.into());
+        self
+    }
+
+    pub fn with_argument(mut self, argument: impl Into<String>) -> Self {
+        self.argument = Some(argument.into());
+        self
+    }
+
+    pub fn with_tensor(mut self, tensor: impl Into<String>) -> Self {
+        self.tensor = Some(tensor.into());
+        self
+    }
+
+    /// Use the stage and argument to figure out where in the Runefile this
+    /// diagnostic refers to.
+    pub fn locate(mut self, src: &str) -> Self {
+        if self.span.is_some() {
+            return self;
+        }
+
+        if let Some(stage) = &self.stage {
+            let stage_span = find_key(src, &["pipeline", stage]);
+
+            self.span = match &self.argument {
+                Some(arg) => find_key(src, &["pipeline", stage, "args", arg]).or(stage_span),
+                None => stage_span,
+            };
+        }
+
+        self
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, TS)]
+#[ts(export, export_to = "../src/backend/types/")]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+/// A location in the Runefile's source text.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, TS)]
+#[ts(export, export_to = "../src/backend/types/")]
+pub struct Span {
+    /// The byte offset this span starts at.
+    pub start: usize,
+    /// The byte offset just past the end of this span.
+    pub end: usize,
+    /// The (1-based) line number.
+    pub line: usize,
+    /// The (1-based) column number.
+    pub column: usize,
+}
+
+/// Which part of the compiler produced an error.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Phase {
+    Parse,
+    TypeCheck,
+    Codegen,
+}
+
+impl Phase {
+    fn code(self) -> &'static str {
+        match self {
+            Phase::Parse => "parse-failed",
+            Phase::TypeCheck => "type-check-failed",
+            Phase::Codegen => "codegen-failed",
+        }
+    }
+}
+
+/// An asset the compiler asked for while type checking or generating code,
+/// but which couldn't be loaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct FailedRead {
+    pub uri: String,
+    /// The proc-block the URI refers to, if any.
+    pub package: Option<(String, Option<String>)>,
+    pub message: String,
+}
+
+impl FailedRead {
+    fn refers_to(&self, reference: &str) -> bool {
+        refers_to(&self.uri, self.package.as_ref(), reference)
+    }
+}
+
+/// Is the asset the compiler asked for (`uri`, which may refer to `package`)
+/// the one a stage's `model` or `proc-block` (or a resource's `path`) refers
+/// to?
+pub(crate) fn refers_to(
+    uri: &str,
+    package: Option<&(String, Option<String>)>,
+    reference: &str,
+) -> bool {
+    if uri == reference {
+        return true;
+    }
+
+    let referenced_package = URI::try_from(reference)
+        .ok()
+        .and_then(|uri| package_reference(&uri))
+        .or_else(|| parse_package(reference));
+    if let (Some(package), Some(referenced)) = (package, referenced_package) {
+        return *package == referenced;
+    }
+
+    // Relative paths get resolved against the project directory
+    let path = reference.trim_start_matches("./");
+    match uri.strip_prefix("file://") {
+        Some(uri_path) => uri_path == path || uri_path.ends_with(&format!("/{path}")),
+        None => false,
+    }
+}
+
+/// Turn an error from one of the compiler's queries into [`Diagnostic`]s.
+///
+/// YAML syntax errors carry their own location. Otherwise, each asset the
+/// compiler failed to load gets its own diagnostic pointing at whichever
+/// stage or resource asked for it. Errors that can't be tied to anything in
+/// the Runefile are reported without a location, because no location is
+/// better than highlighting the wrong node.
+pub(crate) fn from_compile_error(
+    src: &str,
+    phase: Phase,
+    error: &(dyn std::error::Error + 'static),
+    failed_reads: &[FailedRead],
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    if phase != Phase::Parse {
+        if let Ok(runefile) = Runefile::parse(src) {
+            diagnostics.extend(
+                failed_reads
+                    .iter()
+                    .filter_map(|read| asset_diagnostic(src, &runefile, read)),
+            );
+        }
+    }
+
+    if diagnostics.is_empty() {
+        let mut diag = Diagnostic::error(phase.code(), error.to_string());
+        diag.span = yaml_span(src, error);
+        diagnostics.push(diag);
+    }
+
+    diagnostics
+}
+
+/// Point a failed read at the stage or resource that needed it.
+fn asset_diagnostic(src: &str, runefile: &Runefile, read: &FailedRead) -> Option<Diagnostic> {
+    let message = format!("Unable to load \"{}\": {}", read.uri, read.message);
+
+    for (name, stage) in &runefile.pipeline {
+        let references = [
+            ("model", "model-unavailable", stage.model.as_deref()),
+            (
+                "proc-block",
+                "proc-block-unavailable",
+                stage.proc_block.as_deref(),
+            ),
+        ];
+
+        for (key, code, reference) in references {
+            if reference.map_or(false, |r| read.refers_to(r)) {
+                let mut diag = Diagnostic::error(code, message).with_stage(name);
+                diag.span = find_key(src, &["pipeline", name, key]);
+                return Some(diag.locate(src));
+            }
+        }
+    }
+
+    for (name, resource) in &runefile.resources {
+        let path = resource.get("path").and_then(|p| p.as_str());
+
+        if path.map_or(false, |p| read.refers_to(p)) {
+            let mut diag = Diagnostic::error("resource-unavailable", message);
+            diag.span = find_key(src, &["resources", name]);
+            return Some(diag);
+        }
+    }
+
+    None
+}
+
+/// Find the location of a YAML syntax error anywhere in the error's chain.
+fn yaml_span(src: &str, error: &(dyn std::error::Error + 'static)) -> Option<Span> {
+    let mut current = Some(error);
+
+    while let Some(e) = current {
+        if let Some(location) = e
+            .downcast_ref::<serde_yaml::Error>()
+            .and_then(|e| e.location())
+        {
+            return span_at(src, location.line(), location.column());
+        }
+
+        current = e.source();
+    }
+
+    None
+}
+
+/// Get the (empty) span at a 1-based line and column.
+fn span_at(src: &str, line: usize, column: usize) -> Option<Span> {
+    let line_start: usize = src
+        .split('\n')
+        .take(line.checked_sub(1)?)
+        .map(|l| l.len() + 1)
+        .sum();
+    let start = (line_start + column.saturating_sub(1)).min(src.len());
+
+    Some(Span {
+        start,
+        end: start,
+        line,
+        column,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SRC: &str = "pipeline:\n  input:\n    capability: RAW\n  argmax:\n    proc-block: hotg-ai/argmax@0.12\n    inputs:\n      - input\n";
+
+    #[derive(Debug)]
+    struct CodegenError(&'static str);
+
+    impl Display for CodegenError {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            f.write_str(self.0)
+        }
+    }
+
+    impl std::error::Error for CodegenError {}
+
+    fn failed_read(uri: &str, package: Option<(&str, &str)>) -> FailedRead {
+        FailedRead {
+            uri: uri.to_string(),
+            package: package.map(|(name, version)| (name.to_string(), Some(version.to_string()))),
+            message: "Not found".to_string(),
+        }
+    }
+
+    #[test]
+    fn point_failed_reads_at_the_stage_that_needs_them() {
+        let src = "pipeline:\n  input:\n    capability: RAW\n  model:\n    model: ./model.tflite\n    inputs:\n      - input\n  argmax:\n    proc-block: hotg-ai/argmax@0.12\n    inputs:\n      - model\n";
+        let error = CodegenError("Unable to build the Rune");
+        let failed_reads = [
+            failed_read("file:///projects/demo/model.tflite", None),
+            failed_read(
+                "wapm:///hotg-ai/argmax?version=0.12",
+                Some(("hotg-ai/argmax", "0.12")),
+            ),
+            failed_read("file:///projects/other/unrelated.tflite", None),
+        ];
+
+        let diags = from_compile_error(src, Phase::Codegen, &error, &failed_reads);
+
+        assert_eq!(diags.len(), 2);
+        assert_eq!(diags[0].code, "model-unavailable");
+        assert_eq!(diags[0].stage.as_deref(), Some("model"));
+        assert_eq!(diags[0].span.unwrap().line, 5);
+        assert_eq!(diags[1].code, "proc-block-unavailable");
+        assert_eq!(diags[1].stage.as_deref(), Some("argmax"));
+        assert_eq!(diags[1].span.unwrap().line, 9);
+    }
+
+    #[test]
+    fn other_errors_have_no_location() {
+        // Even though this mentions a stage, there's no way to be sure it's
+        // about that stage
+        let error = CodegenError("Unable to load the proc-block for \"argmax\"");
+
+        let diags = from_compile_error(SRC, Phase::Codegen, &error, &[]);
+
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].code, "codegen-failed");
+        assert_eq!(diags[0].stage, None);
+        assert_eq!(diags[0].span, None);
+    }
+
+    #[test]
+    fn yaml_errors_use_their_location() {
+        let src = "pipeline:\n  argmax: [\n";
+        let error = serde_yaml::from_str::<serde_yaml::Value>(src).unwrap_err();
+
+        let diags = from_compile_error(src, Phase::Parse, &error, &[]);
+
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].code, "parse-failed");
+        assert!(diags[0].span.is_some());
+        assert_eq!(diags[0].stage, None);
+    }
+}
@@ -0,0 +1,681 @@
+mod cache;
+mod check;
+mod diagnostics;
+mod progress;
+mod runefile;
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, MutexGuard, PoisonError,
+    },
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Error};
+use hotg_rune_compiler::{
+    asset_loader::AssetLoader,
+    codegen::{Codegen, CodegenStorage},
+    im::Vector,
+    parse::{Frontend, FrontendStorage},
+    BuildConfig, Environment, EnvironmentStorage,
+};
+use salsa::Storage;
+use tauri::{AppHandle, Manager};
+use ts_rs::TS;
+use uriparse::URI;
+use uuid::Uuid;
+
+use crate::{
+    proc_blocks::package_reference,
+    projects::{self, ProjectSettings},
+    shared::SerializableError,
+    AppState, BlobStore,
+};
+
+pub use self::{
+    cache::{clear_compilation_cache, ClearedCache},
+    diagnostics::{CompileFailed, Diagnostic, Severity, Span},
+    progress::{CompilationEvent, CompilationEventKind, CompilationPhase},
+};
+
+pub(crate) use self::runefile::{parse_element_type, Runefile};
+
+/// The content type used when a Rune archive is put in the [`BlobStore`].
+pub(crate) const ARCHIVE_CONTENT_TYPE: &str = "application/zip";
+
+use self::{
+    diagnostics::{FailedRead, Phase},
+    progress::Progress,
+};
+
+/// Compile a Runefile into a Rune, using the settings saved for the `project`
+/// with that name (see [`crate::projects::save_project_settings()`]).
+///
+/// Progress is reported using `compilation_progress` events (see
+/// [`CompilationEvent`]), finishing with either a
+/// [`CompilationEventKind::Succeeded`] or [`CompilationEventKind::Failed`].
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub async fn compile(
+    runefile: String,
+    app: tauri::State<'_, AppState>,
+    databases: tauri::State<'_, Databases>,
+    blobs: tauri::State<'_, BlobStore>,
+    project: String,
+    window: tauri::Window,
+) -> Result<CompileResponse, SerializableError<CompileFailed>> {
+    let progress = Progress::new(window);
+    let settings = project_settings(&app, &project).await?;
+    let db = databases.for_project(&settings);
+
+    let result = run_compilation(&app, &db, &blobs, &settings, runefile.clone(), &progress).await;
+
+    match &result {
+        Ok(response) => {
+            tracing::debug!(cache_stats = ?response.cache_stats, "Compiled the Rune");
+            save_runefile(&app, &project, &runefile).await;
+            progress.emit(CompilationEventKind::Succeeded {
+                cache_stats: response.cache_stats,
+            });
+        }
+        Err(e) => {
+            tracing::warn!(error = &**e as &dyn std::error::Error, "Compilation failed");
+            progress.failed(e);
+        }
+    }
+
+    result.map_err(SerializableError::from)
+}
+
+/// Check a Runefile for problems without doing a full compilation.
+///
+/// This runs the compiler's frontend (parsing and type checking) and checks
+/// the Runefile against the signatures of any proc-blocks in the local cache,
+/// without generating any code, so it is cheap enough to call every time the
+/// user edits their pipeline.
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+pub async fn check_runefile(
+    runefile: String,
+    project: String,
+    app: tauri::State<'_, AppState>,
+    databases: tauri::State<'_, Databases>,
+) -> Result<Vec<Diagnostic>, SerializableError> {
+    let project = project_settings(&app, &project).await?;
+    let cfg = project.build_config()?;
+
+    let frontend_errors = {
+        let db = databases.for_project(&project);
+        let runefile = runefile.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let mut db = lock(&db);
+            db.update(runefile, cfg);
+            let src = db.current_src();
+
+            if let Err(e) = db.parse() {
+                return Some((
+                    Phase::Parse,
+                    diagnostics::from_compile_error(src, Phase::Parse, &e, &[]),
+                ));
+            }
+
+            let e = db.type_check().err()?;
+            Some((
+                Phase::TypeCheck,
+                diagnostics::from_compile_error(src, Phase::TypeCheck, &e, &db.failed_reads()),
+            ))
+        })
+        .await?
+    };
+
+    let mut diagnostics = Vec::new();
+
+    match frontend_errors {
+        // There's no point checking a Runefile we couldn't parse
+        Some((Phase::Parse, errors)) => return Ok(errors),
+        Some((_, errors)) => diagnostics.extend(errors),
+        None => {}
+    }
+
+    let parsed = Runefile::parse(&runefile).context("Unable to parse the Runefile")?;
+    let (signatures, notes) = check::stage_signatures(&app, &runefile, &parsed).await;
+    diagnostics.extend(notes);
+    diagnostics.extend(check::check(&runefile, &parsed, &signatures));
+
+    Ok(diagnostics)
+}
+
+async fn project_settings(app: &AppState, name: &str) -> Result<ProjectSettings, Error> {
+    let conn = app.meta_db().await;
+    projects::load_settings(&conn, app.home_dir(), name)
+}
+
+async fn run_compilation(
+    app: &AppState,
+    db: &Arc<Mutex<Database>>,
+    blobs: &BlobStore,
+    project: &ProjectSettings,
+    runefile: String,
+    progress: &Progress,
+) -> Result<CompileResponse, Error> {
+    let cfg = project.build_config()?;
+    let key = archive_cache_key(app, &runefile, &cfg).await;
+
+    if let Some(rune) = cached_archive(app, key.as_deref()).await {
+        return Ok(CompileResponse {
+            rune: blobs.insert(ARCHIVE_CONTENT_TYPE, rune),
+            cache_stats: CompilerCacheStats {
+                archive_cache_hit: true,
+                ..Default::default()
+            },
+        });
+    }
+
+    let (rune, cache_stats) = {
+        let db = Arc::clone(db);
+        let progress = progress.clone();
+
+        tokio::task::spawn_blocking(move || build(&db, runefile, cfg, &progress))
+            .await
+            .context("Unable to wait for the compiler to finish running")??
+    };
+
+    let rune = progress
+        .phase_async(CompilationPhase::Archiving, async {
+            let rune: Vec<u8> = rune.iter().copied().collect();
+
+            if let Some(key) = &key {
+                save_archive(app, key, &rune).await;
+            }
+
+            blobs.insert(ARCHIVE_CONTENT_TYPE, rune)
+        })
+        .await;
+
+    Ok(CompileResponse { rune, cache_stats })
+}
+
+/// Run the compiler, one phase at a time.
+///
+/// The checks from [`check::check()`] are only advisory, so they don't get a
+/// say in whether the Rune can be compiled.
+fn build(
+    db: &Mutex<Database>,
+    runefile: String,
+    cfg: BuildConfig,
+    progress: &Progress,
+) -> Result<(Vector<u8>, CompilerCacheStats), Error> {
+    let mut db = lock(db);
+    db.update(runefile, cfg);
+    let db = &*db;
+    let src = db.current_src();
+    let _watching = db.watch_assets(progress);
+
+    progress
+        .phase(CompilationPhase::Parsing, || db.parse())
+        .map_err(|e| compile_failed(src, &[], Phase::Parse, e))?;
+
+    progress.phase(CompilationPhase::ResolvingAssets, || resolve_assets(db));
+
+    progress
+        .phase(CompilationPhase::TypeChecking, || db.type_check())
+        .map_err(|e| compile_failed(src, &db.failed_reads(), Phase::TypeCheck, e))?;
+
+    let rune = progress
+        .phase(CompilationPhase::Codegen, || db.rune_archive())
+        .map_err(|e| compile_failed(src, &db.failed_reads(), Phase::Codegen, e))?;
+
+    Ok((rune, db.cache_stats()))
+}
+
+/// Make sure every remote proc-block and model the Runefile needs has been
+/// downloaded before the compiler asks for it.
+///
+/// Assets are loaded through the [`Database`] so the frontend hears about
+/// each one. Failures aren't fatal here, because the compiler will report
+/// them (see [`diagnostics::from_compile_error()`]) if they actually matter.
+fn resolve_assets(db: &Database) {
+    let runefile = Runefile::parse(db.current_src()).unwrap_or_default();
+
+    for uri in runefile.remote_assets() {
+        match URI::try_from(uri) {
+            Ok(uri) => {
+                let _ = AssetLoader::read(db, &uri);
+            }
+            Err(e) => {
+                tracing::debug!(
+                    %uri,
+                    error = &e as &dyn std::error::Error,
+                    "Leaving a malformed asset URI for the compiler to report",
+                );
+            }
+        }
+    }
+}
+
+// Note: Problems with the archive cache are never fatal. Worst case, we just
+// compile the Rune again.
+
+async fn archive_cache_key(app: &AppState, runefile: &str, cfg: &BuildConfig) -> Option<String> {
+    let conn = app.meta_db().await;
+
+    cache::cache_key(&conn, runefile, cfg)
+        .map_err(|e| {
+            tracing::warn!(
+                error = &*e as &dyn std::error::Error,
+                "Unable to calculate the compilation cache key",
+            );
+        })
+        .ok()
+        .flatten()
+}
+
+async fn cached_archive(app: &AppState, key: Option<&str>) -> Option<Vec<u8>> {
+    let key = key?;
+    let conn = app.meta_db().await;
+
+    match cache::lookup(&conn, app.home_dir(), key) {
+        Ok(Some(rune)) => {
+            tracing::debug!(%key, "Using a previously compiled Rune");
+            Some(rune)
+        }
+        Ok(None) => None,
+        Err(e) => {
+            tracing::warn!(
+                error = &*e as &dyn std::error::Error,
+                %key,
+                "Unable to check the compilation cache",
+            );
+            None
+        }
+    }
+}
+
+/// Remember which Runefile the project uses so the proc-blocks it needs
+/// aren't garbage collected.
+async fn save_runefile(app: &AppState, project: &str, runefile: &str) {
+    let conn = app.meta_db().await;
+
+    if let Err(e) = projects::save_runefile(&conn, project, runefile) {
+        tracing::warn!(
+            error = &*e as &dyn std::error::Error,
+            %project,
+            "Unable to save the Runefile",
+        );
+    }
+}
+
+async fn save_archive(app: &AppState, key: &str, rune: &[u8]) {
+    let conn = app.meta_db().await;
+
+    if let Err(e) = cache::store(&conn, app.home_dir(), key, rune, cache::MAX_CACHE_SIZE) {
+        tracing::warn!(
+            error = &*e as &dyn std::error::Error,
+            %key,
+            "Unable to save the compiled Rune",
+        );
+    }
+}
+
+/// Lock the compiler's database.
+///
+/// Salsa already takes care of queries that panicked part-way through, so we
+/// can keep using the database after a previous compilation blew up.
+fn lock(db: &Mutex<Database>) -> MutexGuard<'_, Database> {
+    db.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, TS)]
+#[ts(export, export_to = "../src/backend/types/")]
+pub struct CompileResponse {
+    /// The ID of the compiled Rune archive in the [`BlobStore`].
+    pub rune: Uuid,
+    pub cache_stats: CompilerCacheStats,
+}
+
+/// How much work the compiler was able to reuse from previous compilations.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, TS)]
+#[ts(export, export_to = "../src/backend/types/")]
+pub struct CompilerCacheStats {
+    /// The number of queries that needed to be (re)computed.
+    pub recomputed: usize,
+    /// The number of queries where a previously computed value was reused.
+    pub reused: usize,
+    /// Was the compiled Rune loaded from disk without running the compiler?
+    pub archive_cache_hit: bool,
+}
+
+/// Attach diagnostics to an error from one of the compiler's queries.
+fn compile_failed<E>(src: &str, failed_reads: &[FailedRead], phase: Phase, error: E) -> Error
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    let diagnostics = diagnostics::from_compile_error(src, phase, &error, failed_reads);
+
+    Error::new(error).context(CompileFailed { diagnostics })
+}
+
+/// The maximum number of projects with a compiler database in memory.
+/// Compiling another project when we are at the limit will evict the least
+/// recently used database.
+const MAX_DATABASES: usize = 4;
+
+/// Databases that haven't been used for this long will be dropped.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// How often to check for idle databases.
+const EVICTION_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The compiler's incremental databases, one for each project.
+///
+/// Each project gets its own [`Database`] so compiling (or checking) one
+/// project's Runefile doesn't invalidate everything salsa remembers about
+/// the others. Databases can get quite big, so only the most recently used
+/// ones are kept around (see [`MAX_DATABASES`] and [`IDLE_TIMEOUT`]).
+pub struct Databases {
+    assets: Arc<dyn AssetLoader + Send + Sync>,
+    projects: Mutex<HashMap<PathBuf, ProjectDatabase>>,
+}
+
+struct ProjectDatabase {
+    /// Shared so a compilation can keep going even if the database gets
+    /// evicted part-way through.
+    db: Arc<Mutex<Database>>,
+    last_used: Instant,
+}
+
+impl Databases {
+    pub fn new(assets: Arc<dyn AssetLoader + Send + Sync>) -> Self {
+        Databases {
+            assets,
+            projects: Mutex::default(),
+        }
+    }
+
+    /// Get the database for a project, creating it if this is the first
+    /// time the project is being compiled.
+    pub fn for_project(&self, project: &ProjectSettings) -> Arc<Mutex<Database>> {
+        let mut projects = self.lock();
+
+        if !projects.contains_key(&project.directory) {
+            while projects.len() >= MAX_DATABASES {
+                let oldest = projects
+                    .iter()
+                    .min_by_key(|(_, p)| p.last_used)
+                    .map(|(dir, _)| dir.clone());
+
+                match oldest {
+                    Some(dir) => {
+                        tracing::debug!(dir = %dir.display(), "Evicting the least recently used compiler database");
+                        projects.remove(&dir);
+                    }
+                    None => break,
+                }
+            }
+        }
+
+        let entry = projects
+            .entry(project.directory.clone())
+            .or_insert_with(|| ProjectDatabase {
+                db: Arc::new(Mutex::new(Database::new(Arc::clone(&self.assets)))),
+                last_used: Instant::now(),
+            });
+        entry.last_used = Instant::now();
+
+        Arc::clone(&entry.db)
+    }
+
+    /// Drop any databases which have been idle for longer than
+    /// [`IDLE_TIMEOUT`].
+    fn evict_idle(&self) {
+        let now = Instant::now();
+
+        self.lock().retain(|dir, project| {
+            let keep = now.duration_since(project.last_used) < IDLE_TIMEOUT;
+            if !keep {
+                tracing::debug!(dir = %dir.display(), "Evicting an idle compiler database");
+            }
+            keep
+        });
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<PathBuf, ProjectDatabase>> {
+        self.projects.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Periodically drop compiler databases that haven't been used in a while.
+pub(crate) async fn evict_idle_databases(handle: AppHandle) {
+    let mut interval = tokio::time::interval(EVICTION_INTERVAL);
+
+    loop {
+        interval.tick().await;
+        handle.state::<Databases>().evict_idle();
+    }
+}
+
+/// The compiler's incremental database.
+///
+/// This is meant to be long-lived so salsa can avoid recomputing anything that
+/// isn't affected when the user edits their Runefile.
+#[salsa::database(CodegenStorage, FrontendStorage, EnvironmentStorage)]
+pub struct Database {
+    assets: Arc<dyn AssetLoader + Send + Sync>,
+    storage: Storage<Self>,
+    src: Option<String>,
+    config: Option<BuildConfig>,
+    recomputed: AtomicUsize,
+    reused: AtomicUsize,
+    /// Assets that couldn't be loaded, keyed by URI.
+    ///
+    /// Salsa remembers a query's result (including any errors) until its
+    /// inputs change, so these are kept until a later read succeeds rather
+    /// than being reset for each compilation.
+    failed_reads: Mutex<HashMap<String, FailedRead>>,
+    /// Set while a compilation wants to hear about the assets being loaded.
+    asset_progress: Mutex<Option<AssetProgress>>,
+}
+
+/// Reports the assets loaded during a compilation to the frontend.
+struct AssetProgress {
+    progress: Progress,
+    runefile: Runefile,
+    /// Assets are only reported the first time they are loaded.
+    reported: HashSet<String>,
+}
+
+impl AssetProgress {
+    /// Let the frontend know we've started loading an asset, returning the
+    /// stage it is for, or `None` if it has already been reported.
+    fn start(
+        &mut self,
+        uri: &str,
+        package: Option<&(String, Option<String>)>,
+    ) -> Option<(Progress, Option<String>)> {
+        if !self.reported.insert(uri.to_string()) {
+            return None;
+        }
+
+        let stage = self.runefile.stage_using(uri, package).map(String::from);
+        self.progress.emit(CompilationEventKind::AssetStarted {
+            uri: uri.to_string(),
+            stage: stage.clone(),
+        });
+
+        Some((self.progress.clone(), stage))
+    }
+}
+
+/// Stops reporting assets when dropped.
+struct WatchingAssets<'db>(&'db Database);
+
+impl Drop for WatchingAssets<'_> {
+    fn drop(&mut self) {
+        *self.0.lock_asset_progress() = None;
+    }
+}
+
+impl Database {
+    pub fn new(assets: Arc<dyn AssetLoader + Send + Sync>) -> Self {
+        Database {
+            assets,
+            storage: Storage::default(),
+            src: None,
+            config: None,
+            recomputed: AtomicUsize::new(0),
+            reused: AtomicUsize::new(0),
+            failed_reads: Mutex::default(),
+            asset_progress: Mutex::default(),
+        }
+    }
+
+    /// Prepare for a new compilation, only touching the inputs which have
+    /// actually changed.
+    pub fn update(&mut self, src: String, config: BuildConfig) {
+        self.recomputed.store(0, Ordering::Relaxed);
+        self.reused.store(0, Ordering::Relaxed);
+
+        if self.src.as_ref() != Some(&src) {
+            self.set_src(src.clone().into());
+            self.src = Some(src);
+        }
+
+        if self.config.as_ref() != Some(&config) {
+            self.set_config(config.clone());
+            self.config = Some(config);
+        }
+    }
+
+    fn current_src(&self) -> &str {
+        self.src.as_deref().unwrap_or_default()
+    }
+
+    /// Emit events for each asset loaded until the returned guard is dropped.
+    fn watch_assets(&self, progress: &Progress) -> WatchingAssets<'_> {
+        *self.lock_asset_progress() = Some(AssetProgress {
+            progress: progress.clone(),
+            runefile: Runefile::parse(self.current_src()).unwrap_or_default(),
+            reported: HashSet::new(),
+        });
+
+        WatchingAssets(self)
+    }
+
+    fn lock_asset_progress(&self) -> MutexGuard<'_, Option<AssetProgress>> {
+        self.asset_progress
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Every asset the compiler tried (and failed) to load.
+    fn failed_reads(&self) -> Vec<FailedRead> {
+        let failed_reads = self
+            .failed_reads
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        failed_reads.values().cloned().collect()
+    }
+
+    /// Statistics for the queries executed since the last [`Database::update()`].
+    pub fn cache_stats(&self) -> CompilerCacheStats {
+        CompilerCacheStats {
+            recomputed: self.recomputed.load(Ordering::Relaxed),
+            reused: self.reused.load(Ordering::Relaxed),
+            archive_cache_hit: false,
+        }
+    }
+}
+
+impl salsa::Database for Database {
+    fn salsa_event(&self, event: salsa::Event) {
+        match event.kind {
+            salsa::EventKind::WillExecute { .. } => {
+                self.recomputed.fetch_add(1, Ordering::Relaxed);
+            }
+            salsa::EventKind::DidValidateMemoizedValue { .. } => {
+                self.reused.fetch_add(1, Ordering::Relaxed);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl AssetLoader for Database {
+    fn read(
+        &self,
+        path: &uriparse::URI<'_>,
+    ) -> Result<hotg_rune_compiler::im::Vector<u8>, hotg_rune_compiler::asset_loader::ReadError>
+    {
+        let uri = path.to_string();
+        let package = package_reference(path);
+        let reporting = self
+            .lock_asset_progress()
+            .as_mut()
+            .and_then(|p| p.start(&uri, package.as_ref()));
+
+        let result = self.assets.read(path);
+
+        if let Some((progress, stage)) = reporting {
+            progress.emit(match &result {
+                Ok(bytes) => CompilationEventKind::AssetFinished {
+                    uri: uri.clone(),
+                    stage,
+                    bytes: bytes.len() as u64,
+                },
+                Err(e) => CompilationEventKind::AssetFailed {
+                    uri: uri.clone(),
+                    stage,
+                    error: e.to_string(),
+                },
+            });
+        }
+
+        let mut failed_reads = self
+            .failed_reads
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        match &result {
+            Ok(_) => {
+                failed_reads.remove(&uri);
+            }
+            Err(e) => {
+                failed_reads.insert(
+                    uri.clone(),
+                    FailedRead {
+                        uri,
+                        package,
+                        message: e.to_string(),
+                    },
+                );
+            }
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failed_compilations_serialize_their_diagnostics() {
+        let src = "pipeline:\n  argmax: [\n";
+        let error = serde_yaml::from_str::<serde_yaml::Value>(src).unwrap_err();
+
+        let error: SerializableError<CompileFailed> =
+            compile_failed(src, &[], Phase::Parse, error).into();
+
+        let state = error.get_state().unwrap();
+        assert_eq!(state.diagnostics.len(), 1);
+        assert_eq!(state.diagnostics[0].code, "parse-failed");
+        let serialized = serde_json::to_value(&error).unwrap();
+        assert!(serialized.get("state").is_some());
+    }
+}
@@ -0,0 +1,144 @@
+use std::{future::Future, time::Instant};
+
+use ts_rs::TS;
+
+use crate::compiler::{CompileFailed, CompilerCacheStats, Diagnostic};
+
+/// Something that happened while compiling a Rune, emitted as a
+/// `compilation_progress` event.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, TS)]
+#[ts(export, export_to = "../src/backend/types/")]
+pub struct CompilationEvent {
+    /// How long it has been since compilation started.
+    #[ts(type = "number")]
+    pub elapsed_ms: u64,
+    pub kind: CompilationEventKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, TS)]
+#[ts(export, export_to = "../src/backend/types/")]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum CompilationEventKind {
+    PhaseStarted {
+        phase: CompilationPhase,
+    },
+    PhaseFinished {
+        phase: CompilationPhase,
+        /// How long this phase took.
+        #[ts(type = "number")]
+        duration_ms: u64,
+    },
+    /// The compiler has started loading an asset (e.g. a proc-block or
+    /// model), possibly from the network.
+    AssetStarted {
+        uri: String,
+        /// The pipeline stage that uses this asset, if we can tell.
+        stage: Option<String>,
+    },
+    AssetFinished {
+        uri: String,
+        stage: Option<String>,
+        #[ts(type = "number")]
+        bytes: u64,
+    },
+    AssetFailed {
+        uri: String,
+        stage: Option<String>,
+        error: String,
+    },
+    /// Compilation finished successfully. This is always the last event.
+    Succeeded {
+        cache_stats: CompilerCacheStats,
+    },
+    /// Compilation failed. This is always the last event.
+    Failed {
+        error: String,
+        diagnostics: Vec<Diagnostic>,
+    },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, TS)]
+#[ts(export, export_to = "../src/backend/types/")]
+#[serde(rename_all = "snake_case")]
+pub enum CompilationPhase {
+    Parsing,
+    /// Fetching the proc-blocks and models the Runefile refers to.
+    ResolvingAssets,
+    TypeChecking,
+    Codegen,
+    /// Saving the compiled Rune.
+    Archiving,
+}
+
+/// Emits [`CompilationEvent`]s to the frontend.
+#[derive(Clone)]
+pub(crate) struct Progress {
+    window: tauri::Window,
+    started: Instant,
+}
+
+impl Progress {
+    pub fn new(window: tauri::Window) -> Self {
+        Progress {
+            window,
+            started: Instant::now(),
+        }
+    }
+
+    pub fn emit(&self, kind: CompilationEventKind) {
+        let event = CompilationEvent {
+            elapsed_ms: self.started.elapsed().as_millis() as u64,
+            kind,
+        };
+
+        if let Err(e) = self.window.emit("compilation_progress", &event) {
+            tracing::warn!(
+                error = &e as &dyn std::error::Error,
+                "Unable to emit a compilation event",
+            );
+        }
+    }
+
+    /// Run a phase of the compilation, letting the frontend know when it
+    /// starts and finishes.
+    pub fn phase<T>(&self, phase: CompilationPhase, run: impl FnOnce() -> T) -> T {
+        self.emit(CompilationEventKind::PhaseStarted { phase });
+        let started = Instant::now();
+
+        let result = run();
+
+        self.emit(CompilationEventKind::PhaseFinished {
+            phase,
+            duration_ms: started.elapsed().as_millis() as u64,
+        });
+
+        result
+    }
+
+    pub async fn phase_async<F: Future>(&self, phase: CompilationPhase, run: F) -> F::Output {
+        self.emit(CompilationEventKind::PhaseStarted { phase });
+        let started = Instant::now();
+
+        let result = run.await;
+
+        self.emit(CompilationEventKind::PhaseFinished {
+            phase,
+            duration_ms: started.elapsed().as_millis() as u64,
+        });
+
+        result
+    }
+
+    /// Let the frontend know compilation failed.
+    pub fn failed(&self, error: &anyhow::Error) {
+        let diagnostics = error
+            .downcast_ref::<CompileFailed>()
+            .map(|c| c.diagnostics.clone())
+            .unwrap_or_default();
+
+        self.emit(CompilationEventKind::Failed {
+            error: format!("{error:#}"),
+            diagnostics,
+        });
+    }
+}
@@ -0,0 +1,293 @@
+//! A lightweight model of the `Runefile.yml` format.
+//!
+//! The compiler does its own (much more thorough) parsing, but it doesn't
+//! tell us where each item came from. This lets us point diagnostics at the
+//! stages, arguments and tensors in the original source text.
+
+use std::collections::BTreeMap;
+
+use crate::{
+    compiler::diagnostics::{refers_to, Span},
+    shared::ElementType,
+};
+
+#[derive(Debug, Default, Clone, PartialEq, serde::Deserialize)]
+pub(crate) struct Runefile {
+    #[serde(default)]
+    pub pipeline: BTreeMap<String, Stage>,
+    #[serde(default)]
+    pub resources: BTreeMap<String, serde_yaml::Value>,
+}
+
+impl Runefile {
+    pub fn parse(src: &str) -> Result<Self, serde_yaml::Error> {
+        serde_yaml::from_str(src)
+    }
+
+    /// Every proc-block or model which needs to be fetched from somewhere
+    /// else.
+    pub fn remote_assets(&self) -> impl Iterator<Item = &str> + '_ {
+        self.pipeline
+            .values()
+            .flat_map(|stage| [stage.proc_block.as_deref(), stage.model.as_deref()])
+            .flatten()
+            .filter(|uri| is_remote(uri))
+    }
+
+    /// The name of the stage whose `model` or `proc-block` is the asset the
+    /// compiler asked for.
+    pub fn stage_using(
+        &self,
+        uri: &str,
+        package: Option<&(String, Option<String>)>,
+    ) -> Option<&str> {
+        self.pipeline
+            .iter()
+            .find(|(_, stage)| {
+                [stage.model.as_deref(), stage.proc_block.as_deref()]
+                    .into_iter()
+                    .flatten()
+                    .any(|reference| refers_to(uri, package, reference))
+            })
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Every file on disk the Runefile refers to (e.g. `model: ./model.tflite`
+    /// or a resource's `path`), relative to the project directory.
+    pub fn local_files(&self) -> impl Iterator<Item = &str> + '_ {
+        let models = self
+            .pipeline
+            .values()
+            .filter_map(|stage| stage.model.as_deref());
+        let resources = self
+            .resources
+            .values()
+            .filter_map(|resource| resource.get("path")?.as_str());
+
+        models.chain(resources).filter_map(local_path)
+    }
+
+    /// The tensors a stage accepts, in order, as declared by the Runefile.
+    ///
+    /// Capabilities are fed directly by whoever is running the Rune, so
+    /// their declared outputs are used instead. Inputs that refer to an
+    /// unknown stage or output will be `None`.
+    pub fn declared_inputs(&self, stage: &str) -> Option<Vec<Option<&TensorType>>> {
+        let stage = self.pipeline.get(stage)?;
+
+        if stage.capability.is_some() {
+            return Some(stage.outputs.iter().map(Some).collect());
+        }
+
+        let inputs = stage
+            .inputs
+            .iter()
+            .map(|input| {
+                let (upstream, index) = output_reference(input);
+                self.pipeline.get(upstream)?.outputs.get(index)
+            })
+            .collect();
+
+        Some(inputs)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct Stage {
+    pub model: Option<String>,
+    pub proc_block: Option<String>,
+    pub capability: Option<String>,
+    pub out: Option<String>,
+    #[serde(default)]
+    pub inputs: Vec<String>,
+    #[serde(default)]
+    pub outputs: Vec<TensorType>,
+    #[serde(default)]
+    pub args: BTreeMap<String, serde_yaml::Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+pub(crate) struct TensorType {
+    #[serde(rename = "type")]
+    pub element_type: String,
+    #[serde(default)]
+    pub dimensions: Vec<usize>,
+}
+
+/// Inputs can refer to a particular output (e.g. `fft.1`), otherwise they
+/// use the stage's first output.
+pub(crate) fn output_reference(input: &str) -> (&str, usize) {
+    match input.rsplit_once('.') {
+        Some((stage, index)) => match index.parse() {
+            Ok(index) => (stage, index),
+            Err(_) => (input, 0),
+        },
+        None => (input, 0),
+    }
+}
+
+/// Parse the element type used in a Runefile (e.g. `f32`).
+pub(crate) fn parse_element_type(s: &str) -> Option<ElementType> {
+    let element_type = match s.to_lowercase().as_str() {
+        "u8" => ElementType::U8,
+        "i8" => ElementType::I8,
+        "u16" => ElementType::U16,
+        "i16" => ElementType::I16,
+        "u32" => ElementType::U32,
+        "i32" => ElementType::I32,
+        "f32" => ElementType::F32,
+        "u64" => ElementType::U64,
+        "i64" => ElementType::I64,
+        "f64" => ElementType::F64,
+        "utf8" | "string" => ElementType::Utf8,
+        _ => return None,
+    };
+
+    Some(element_type)
+}
+
+/// Get the path a URI refers to, if it is a file on disk.
+fn local_path(uri: &str) -> Option<&str> {
+    match uri.strip_prefix("file://") {
+        Some(path) => Some(path),
+        None if uri.contains("://") => None,
+        None => Some(uri),
+    }
+}
+
+fn is_remote(uri: &str) -> bool {
+    ["wapm://", "http://", "https://"]
+        .iter()
+        .any(|scheme| uri.starts_with(scheme))
+}
+
+/// Find the key at a particular path in a YAML document (e.g.
+/// `["pipeline", "argmax", "args"]`).
+///
+/// This only understands block-style mappings, which is what the frontend
+/// generates, and gives up on anything more exotic.
+pub(crate) fn find_key(src: &str, path: &[&str]) -> Option<Span> {
+    let mut lines = lines_with_offsets(src);
+    // The indentation of the parent item's key, or None at the top level
+    let mut parent_indent: Option<usize> = None;
+    let mut span = None;
+
+    for segment in path {
+        let found = lines.by_ref().find_map(|(line_number, offset, line)| {
+            let trimmed = line.trim_start();
+            let indent = line.len() - trimmed.len();
+
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                return None;
+            }
+
+            if let Some(parent) = parent_indent {
+                if indent <= parent {
+                    // We've left the parent's block without finding the key.
+                    return Some(None);
+                }
+            }
+
+            let key = trimmed
+                .split(':')
+                .next()?
+                .trim()
+                .trim_matches(|c| c == '"' || c == '\'');
+
+            if key == *segment && trimmed.contains(':') {
+                let start = offset + indent;
+                Some(Some((
+                    indent,
+                    Span {
+                        start,
+                        end: start + segment.len(),
+                        line: line_number + 1,
+                        column: indent + 1,
+                    },
+                )))
+            } else {
+                None
+            }
+        })?;
+
+        let (indent, s) = found?;
+        parent_indent = Some(indent);
+        span = Some(s);
+    }
+
+    span
+}
+
+fn lines_with_offsets(src: &str) -> impl Iterator<Item = (usize, usize, &str)> {
+    let mut offset = 0;
+
+    src.split('\n').enumerate().map(move |(line_number, line)| {
+        let start = offset;
+        offset += line.len() + 1;
+        (line_number, start, line.trim_end_matches('\r'))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RUNEFILE: &str = r#"version: 1
+image: runicos/base
+pipeline:
+  input:
+    capability: RAW
+    outputs:
+      - type: f64
+        dimensions: [1, 4]
+  argmax:
+    proc-block: "wapm:///hotg-ai/argmax?version=0.12.0"
+    inputs:
+      - input
+    args:
+      element_type: f64
+"#;
+
+    #[test]
+    fn parse_the_runefile() {
+        let runefile = Runefile::parse(RUNEFILE).unwrap();
+
+        let argmax = &runefile.pipeline["argmax"];
+        assert_eq!(argmax.inputs, ["input"]);
+        assert!(argmax.args.contains_key("element_type"));
+        assert_eq!(runefile.pipeline["input"].outputs[0].dimensions, [1, 4]);
+    }
+
+    #[test]
+    fn find_the_stage_using_an_asset() {
+        let runefile = Runefile::parse(RUNEFILE).unwrap();
+        let uri = "wapm:///hotg-ai/argmax?version=0.12.0";
+
+        assert_eq!(runefile.remote_assets().collect::<Vec<_>>(), [uri]);
+        assert_eq!(runefile.stage_using(uri, None), Some("argmax"));
+        assert_eq!(
+            runefile.stage_using(
+                "wapm:///hotg-ai/argmax@0.12.0",
+                Some(&("hotg-ai/argmax".to_string(), Some("0.12.0".to_string())))
+            ),
+            Some("argmax")
+        );
+        assert_eq!(runefile.stage_using("file:///model.tflite", None), None);
+    }
+
+    #[test]
+    fn locate_a_nested_key() {
+        let span = find_key(RUNEFILE, &["pipeline", "argmax", "args", "element_type"]).unwrap();
+
+        assert_eq!(&RUNEFILE[span.start..span.end], "element_type");
+        assert_eq!(span.line, 14);
+        assert_eq!(span.column, 7);
+    }
+
+    #[test]
+    fn keys_are_only_found_inside_their_parent() {
+        // "inputs" exists, but not under the "input" stage
+        assert!(find_key(RUNEFILE, &["pipeline", "input", "inputs"]).is_none());
+    }
+}
@@ -1,6 +1,9 @@
 use std::{collections::HashMap, sync::Arc};
 
-use crate::shared::{ElementType, PaginationConfig, Schema, SerializableError};
+use crate::shared::{
+    Dimensions, ElementType, Incompatibility, PaginationConfig, Schema, SerializableError, Tensor,
+    TensorSignature,
+};
 use arrow::array::{Array, BooleanArray, Int32Array, StringArray, StructArray};
 use ts_rs::TS;
 use uuid::Uuid;
@@ -27,18 +30,29 @@ pub async fn create_dataset(name: &str, _sql: &str) -> Result<DatasetInfo, Seria
     let tensor_info = tensors.iter().map(|(id, t)| TensorInfo {
         id: id.to_string(),
         display_name: t.display_name.to_string(),
-        dimensions: t.dimensions.clone(),
-        element_type: t.element_type,
+        dimensions: t.dimensions(),
+        element_type: t.tensor.element_type,
     });
 
     Ok(DatasetInfo::new(id, name, &table, tensor_info))
 }
 
-fn tensors(_table: &StructArray, _dataset_name: &str) -> HashMap<Uuid, Tensor> {
+fn tensors(_table: &StructArray, _dataset_name: &str) -> HashMap<Uuid, RegisteredTensor> {
     // TODO: copy this from the weld experiment
     HashMap::new()
 }
 
+/// Check whether a dataset's tensor could be passed to a node's input,
+/// returning the reason if it can't.
+#[tracing::instrument]
+#[tauri::command]
+pub fn check_tensor_compatibility(
+    tensor: TensorInfo,
+    input: TensorSignature,
+) -> Option<Incompatibility> {
+    input.accepts(tensor.element_type, &tensor.dimensions).err()
+}
+
 #[tracing::instrument(err)]
 #[tauri::command]
 pub fn list_datasets() -> Result<Vec<DatasetInfo>, SerializableError> {
@@ -64,7 +78,9 @@ pub fn read_dataset_page(
 #[ts(export, export_to = "../src/backend/types/")]
 pub struct DatasetPage {
     pub total_records: usize,
-    pub table: Vec<u8>,
+    /// The ID of a blob containing the page's records as an Apache Arrow IPC
+    /// stream.
+    pub table: Uuid,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, TS, serde::Serialize, serde::Deserialize)]
@@ -101,14 +117,27 @@ impl DatasetInfo {
 pub struct TensorInfo {
     pub id: String,
     pub display_name: String,
-    pub dimensions: Vec<usize>,
+    /// The tensor's shape, where the first dimension is usually `?` because
+    /// datasets are processed in batches of any size.
+    pub dimensions: Dimensions,
     pub element_type: ElementType,
 }
 
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
-struct Tensor {
+struct RegisteredTensor {
     display_name: String,
-    dimensions: Vec<usize>,
-    element_type: ElementType,
-    buffer: Vec<u8>,
+    tensor: Tensor,
+}
+
+impl RegisteredTensor {
+    /// The tensor's shape, treating the first dimension (the number of rows)
+    /// as a wildcard.
+    fn dimensions(&self) -> Dimensions {
+        let mut dimensions: Vec<_> = self.tensor.dimensions.iter().copied().map(Some).collect();
+        if let Some(rows) = dimensions.first_mut() {
+            *rows = None;
+        }
+
+        Dimensions::Fixed(dimensions)
+    }
 }
@@ -1,15 +1,21 @@
 use std::{
-    collections::HashMap,
     fmt::Display,
     path::Path,
     sync::atomic::{AtomicBool, Ordering},
 };
 
-use anyhow::{anyhow, Context, Error};
+use anyhow::Error;
 use arrow::{json, record_batch::RecordBatch};
-use hotg_rune_runtime::zune::{ElementType, TensorResult, ZuneEngine};
-
-use crate::AppState;
+use uuid::Uuid;
+
+use crate::{
+    runtime::{
+        is_cancelled, load_engine, wait_for, Capture, ConfiguredLimits, InputBinding, RunFailed,
+        RunningAnalyses,
+    },
+    shared::{RuneOutputs, SerializableError},
+    AppState, BlobStore,
+};
 
 #[derive(Debug, Default)]
 pub struct Running(pub AtomicBool);
@@ -243,73 +249,72 @@ pub async fn run_sql(
     Ok(())
 }
 
+/// Run a Rune once.
+///
+/// The run can be stopped using [`crate::runtime::cancel_analysis`] with the
+/// `run_id`, in which case it fails with [`RunFailed::Cancelled`] and a
+/// `"run: Cancelled"` progress event is emitted.
 #[tauri::command]
 #[tracing::instrument(skip_all, err)]
 pub async fn reune(
     window: tauri::Window,
-    zune: Vec<u8>,
-    input_tensors: HashMap<String, MyTensor>,
-) -> Result<HashMap<String, MyTensor>, SeriazableError> {
-    let mut zune_engine = ZuneEngine::load(&zune).context("Unable to initialize Zune Engine!")?;
-    tracing::info!(input_nodes = ?zune_engine.input_nodes(), output_nodes=?zune_engine.output_nodes());
-    for (name, tensor) in input_tensors {
-        tracing::info!("Input Name: {name}");
-        window
-            .emit(
-                "reune_progress",
-                &format!("run: Setting Input Name: {name}"),
-            )
-            .map_err(Error::from)?;
-        let input_tensor_node_names = zune_engine
-            .get_input_tensor_names(&name)
-            .with_context(|| format!("Unable to find column: {name}"))?;
-
-        let default_tensor_name = &input_tensor_node_names[0];
-
-        let tensor = TensorResult::from(tensor);
-        tracing::info!(
-          %name,
-          tensor_name=default_tensor_name.as_str(),
-          ?tensor.element_type,
-          ?tensor.dimensions,
-          buffer_length = tensor.buffer.len(),
-          "Setting an input tensor",
-        );
-
-        zune_engine.set_input_tensor(&name, default_tensor_name, &tensor);
-    }
+    state: tauri::State<'_, AppState>,
+    blobs: tauri::State<'_, BlobStore>,
+    limits: tauri::State<'_, ConfiguredLimits>,
+    analyses: tauri::State<'_, RunningAnalyses>,
+    zune: Uuid,
+    input_tensors: Vec<InputBinding>,
+    debug: Option<bool>,
+    run_id: Option<Uuid>,
+) -> Result<RuneOutputs, SerializableError<RunFailed>> {
+    let registration = analyses.register(run_id.unwrap_or_else(Uuid::new_v4))?;
+    let cancellation = registration.token();
+    let zune = blobs.get_bytes(zune)?;
+    let capture = debug
+        .unwrap_or(false)
+        .then(|| Capture::new(state.home_dir()));
+    let mut zune_engine = load_engine(zune, &limits, cancellation).await?;
+    let worker = zune_engine.worker();
+    let limits = limits.get();
 
     window
         .emit("reune_progress", "run: Starting Run")
         .map_err(Error::from)?;
-    if let Err(e) = zune_engine.predict() {
-        tracing::error!(error = &*e as &dyn std::error::Error, "Unable to predict");
-        window
-            .emit("reune_progress", "run: Run failed")
-            .map_err(Error::from)?;
-        return Err(e.into());
-    }
-
-    let output_node = zune_engine.output_nodes()[0].to_string();
-    let output_node_input_names = zune_engine.get_input_tensor_names(&output_node)?;
-
-    let output_tensors: Result<HashMap<String, MyTensor>, Error> = output_node_input_names
-        .iter()
-        .map(|tensor_name| -> Result<(String, MyTensor), Error> {
-            let tensor = zune_engine
-                .get_input_tensor(&output_node, tensor_name)
-                .ok_or_else(|| anyhow!("Unable to fetch output tensor: {tensor_name}"))?;
-            Ok((tensor_name.to_string(), MyTensor::from(tensor)))
-        })
-        .collect();
+    let task =
+        tokio::task::spawn_blocking(move || zune_engine.predict(input_tensors, capture.as_ref()));
+    let outputs = match cancellation
+        .guard(&worker, wait_for(task, &worker, &limits))
+        .await
+        .and_then(|result| result)
+    {
+        Ok(outputs) => outputs,
+        Err(e) if is_cancelled(&e) => {
+            tracing::info!(run_id = %cancellation.run_id(), "The run was cancelled");
+            window
+                .emit("reune_progress", "run: Cancelled")
+                .map_err(Error::from)?;
+            return Err(e.into());
+        }
+        Err(e) => {
+            tracing::error!(error = &*e as &dyn std::error::Error, "Unable to predict");
+            window
+                .emit("reune_progress", "run: Run failed")
+                .map_err(Error::from)?;
+            return Err(e.into());
+        }
+    };
 
-    tracing::debug!("Received the result: {:?}", output_node_input_names);
+    tracing::debug!(
+        output_nodes = ?outputs.nodes.keys(),
+        elapsed_us = outputs.profile.elapsed_us,
+        "Received the result",
+    );
 
     window
         .emit("reune_progress", "run: Successfully Received the result")
         .map_err(Error::from)?;
 
-    Ok(output_tensors?)
+    Ok(outputs)
 }
 
 #[derive(Debug, serde::Serialize)]
@@ -334,86 +339,3 @@ impl Display for SeriazableError {
         write!(f, "{}", self.error)
     }
 }
-
-#[derive(serde::Serialize, serde::Deserialize)]
-pub enum MyTensorDimensions {
-    Dynamic,
-    Fixed(Vec<u32>),
-}
-
-#[derive(serde::Serialize, serde::Deserialize)]
-pub enum MyElementType {
-    U8,
-    I8,
-    U16,
-    I16,
-    U32,
-    I32,
-    F32,
-    U64,
-    I64,
-    F64,
-    /// A string as UTF-8 encoded bytes.
-    Utf8,
-}
-
-#[derive(serde::Serialize, serde::Deserialize)]
-pub struct MyTensor {
-    element_type: MyElementType,
-    dimensions: Vec<u32>,
-    buffer: Vec<u8>,
-}
-
-impl From<TensorResult> for MyTensor {
-    fn from(item: TensorResult) -> Self {
-        MyTensor {
-            element_type: my_element_type(item.element_type),
-            dimensions: item.dimensions,
-            buffer: item.buffer,
-        }
-    }
-}
-
-impl From<MyTensor> for TensorResult {
-    fn from(item: MyTensor) -> Self {
-        TensorResult {
-            element_type: element_type(item.element_type),
-            dimensions: item.dimensions,
-            buffer: item.buffer,
-        }
-    }
-}
-
-fn my_element_type(x: ElementType) -> MyElementType {
-    match x {
-        ElementType::U8 => MyElementType::U8,
-        ElementType::I8 => MyElementType::I8,
-        ElementType::U16 => MyElementType::U16,
-        ElementType::I16 => MyElementType::I16,
-        ElementType::U32 => MyElementType::U32,
-        ElementType::I32 => MyElementType::I32,
-        ElementType::F32 => MyElementType::F32,
-        ElementType::U64 => MyElementType::U64,
-        ElementType::I64 => MyElementType::I64,
-        ElementType::F64 => MyElementType::F64,
-        // A string as UTF-8 encoded bytes. => MyElementType::/// A string as UTF-8 encoded bytes.
-        ElementType::Utf8 => MyElementType::Utf8,
-    }
-}
-
-fn element_type(x: MyElementType) -> ElementType {
-    match x {
-        MyElementType::U8 => ElementType::U8,
-        MyElementType::I8 => ElementType::I8,
-        MyElementType::U16 => ElementType::U16,
-        MyElementType::I16 => ElementType::I16,
-        MyElementType::U32 => ElementType::U32,
-        MyElementType::I32 => ElementType::I32,
-        MyElementType::F32 => ElementType::F32,
-        MyElementType::U64 => ElementType::U64,
-        MyElementType::I64 => ElementType::I64,
-        MyElementType::F64 => ElementType::F64,
-        // A string as UTF-8 encoded bytes. => MyElementType::/// A string as UTF-8 encoded bytes.
-        MyElementType::Utf8 => ElementType::Utf8,
-    }
-}
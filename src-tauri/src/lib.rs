@@ -1,9 +1,13 @@
 mod app;
 mod app_state;
+mod blobs;
+mod bundles;
 mod compiler;
 mod datasets;
 mod legacy;
 mod logging;
+mod proc_blocks;
+mod projects;
 pub mod runtime;
 pub mod shared;
 mod sql;
@@ -12,7 +16,9 @@ mod wapm;
 pub use crate::{
     app::configure,
     app_state::AppState,
-    compiler::{compile, Database},
+    blobs::BlobStore,
+    compiler::{compile, CompileResponse, CompilerCacheStats, Database, Databases},
     datasets::{create_dataset, DatasetInfo},
+    projects::ProjectSettings,
     wapm::known_proc_blocks,
 };
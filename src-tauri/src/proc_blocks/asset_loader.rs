@@ -0,0 +1,459 @@
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use anyhow::{Context, Error};
+use duckdb::{params, Connection, OptionalExt};
+use hotg_rune_compiler::{
+    asset_loader::{AssetLoader, ReadError},
+    im::Vector,
+};
+use semver::Version;
+use uriparse::URI;
+
+use crate::shared::AssetCacheStats;
+
+/// An [`AssetLoader`] which resolves proc-blocks using the packages that have
+/// already been downloaded to the proc-block cache.
+///
+/// Proc-blocks can be referred to using `wapm://` URIs, their public URL, or
+/// a scheme-less reference like `hotg-ai/argmax@0.12`. The compiler treats
+/// scheme-less references as paths relative to the Runefile, so a `file://`
+/// URI that doesn't exist on disk is also checked against the cache.
+///
+/// Anything that isn't in the cache is passed through to a fallback loader,
+/// although requests which would hit the network can be disabled so
+/// compilation works completely offline.
+pub struct LocalAssetLoader {
+    meta_db: Mutex<Connection>,
+    fallback: Arc<dyn AssetLoader + Send + Sync>,
+    allow_network: AtomicBool,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl LocalAssetLoader {
+    pub fn new(meta_db: Connection, fallback: Arc<dyn AssetLoader + Send + Sync>) -> Self {
+        LocalAssetLoader {
+            meta_db: Mutex::new(meta_db),
+            fallback,
+            allow_network: AtomicBool::new(true),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn set_allow_network(&self, allow_network: bool) {
+        self.allow_network.store(allow_network, Ordering::Relaxed);
+    }
+
+    pub fn stats(&self) -> AssetCacheStats {
+        AssetCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            allow_network: self.allow_network.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Try to find the `*.wasm` file a URI refers to in the proc-block cache.
+    fn lookup(&self, uri: &URI<'_>) -> Result<Option<String>, Error> {
+        let conn = self
+            .meta_db
+            .lock()
+            .map_err(|_| Error::msg("The meta.db connection was poisoned"))?;
+
+        if let Some((name, version)) = package_reference(uri) {
+            return find_file_location(&conn, &name, version.as_deref());
+        }
+
+        match uri.scheme().as_str() {
+            "http" | "https" => conn
+                .query_row(
+                    "SELECT fileLoc FROM proc_blocks WHERE publicUrl = ? ORDER BY createdAt DESC LIMIT 1",
+                    params![uri.to_string()],
+                    |row| row.get(0),
+                )
+                .optional()
+                .context("Unable to query the proc-block cache"),
+            _ => Ok(None),
+        }
+    }
+}
+
+impl AssetLoader for LocalAssetLoader {
+    #[tracing::instrument(skip(self), fields(uri = %uri))]
+    fn read(&self, uri: &URI<'_>) -> Result<Vector<u8>, ReadError> {
+        let is_remote =
+            matches!(uri.scheme().as_str(), "http" | "https") || package_reference(uri).is_some();
+
+        if !is_remote {
+            return self.fallback.read(uri);
+        }
+
+        match self.lookup(uri) {
+            Ok(Some(file_loc)) => match std::fs::read(&file_loc) {
+                Ok(wasm) => {
+                    self.hits.fetch_add(1, Ordering::Relaxed);
+                    tracing::debug!(%file_loc, "Proc-block cache hit");
+                    return Ok(wasm.into());
+                }
+                Err(e) => {
+                    // The database and the filesystem have gotten out of
+                    // sync. Treat it like a cache miss.
+                    tracing::warn!(
+                        error = &e as &dyn std::error::Error,
+                        %file_loc,
+                        "Unable to read a cached proc-block",
+                    );
+                }
+            },
+            Ok(None) => {}
+            Err(e) => {
+                tracing::warn!(
+                    error = &*e as &dyn std::error::Error,
+                    "Unable to check the proc-block cache",
+                );
+            }
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        tracing::debug!("Proc-block cache miss");
+
+        if !self.allow_network.load(Ordering::Relaxed) {
+            let e = Error::msg(format!(
+                "\"{uri}\" isn't in the proc-block cache and network access is disabled"
+            ));
+            return Err(ReadError::other(e));
+        }
+
+        self.fallback.read(uri)
+    }
+}
+
+/// Figure out which package a URI refers to, if it refers to one at all.
+pub(crate) fn package_reference(uri: &URI<'_>) -> Option<(String, Option<String>)> {
+    match uri.scheme().as_str() {
+        "wapm" => wapm_package(uri).ok(),
+        "file" => {
+            let path = uri.path().to_string();
+            if Path::new(&path).exists() {
+                return None;
+            }
+
+            let mut segments = path.rsplit('/');
+            let name = segments.next()?;
+            let namespace = segments.next()?;
+            parse_package(&format!("{namespace}/{name}"))
+        }
+        _ => None,
+    }
+}
+
+/// Parse a scheme-less package reference like `hotg-ai/argmax@0.12` or
+/// `hotg-ai/argmax`.
+pub(crate) fn parse_package(reference: &str) -> Option<(String, Option<String>)> {
+    let (name, version) = match reference.split_once('@') {
+        Some((name, version)) => (name, Some(version)),
+        None => (reference, None),
+    };
+
+    validate_package_name(name).ok()?;
+    if let Some(version) = version {
+        validate_version(version).ok()?;
+    }
+
+    Some((name.to_string(), version.map(str::to_string)))
+}
+
+/// Check that a name is of the form `namespace/name`.
+///
+/// This is what [`parse_package()`] accepts, so anything installed under a
+/// valid name can be referred to from a Runefile. Package names also end up
+/// being used as directory names inside the cache, so this makes sure they
+/// can't be used to escape it.
+pub(crate) fn validate_package_name(name: &str) -> Result<(), Error> {
+    let segments: Vec<&str> = name.split('/').collect();
+
+    if segments.len() != 2 {
+        anyhow::bail!("\"{name}\" should be of the form \"namespace/name\"");
+    }
+
+    for segment in segments {
+        validate_path_segment(segment, &['-', '_', '.'])
+            .with_context(|| format!("\"{name}\" isn't a valid name"))?;
+    }
+
+    Ok(())
+}
+
+/// Check that a version (e.g. `0.12.0`, `0.12` or `1.0.0+local`) can be used
+/// in a package reference and as a directory name.
+pub(crate) fn validate_version(version: &str) -> Result<(), Error> {
+    validate_path_segment(version, &['-', '_', '.', '+'])
+        .with_context(|| format!("\"{version}\" isn't a valid version"))
+}
+
+fn validate_path_segment(segment: &str, punctuation: &[char]) -> Result<(), Error> {
+    if segment.is_empty() || segment.starts_with('.') {
+        anyhow::bail!("Segments can't be empty or start with a \".\"");
+    }
+
+    if let Some(c) = segment
+        .chars()
+        .find(|c| !c.is_ascii_alphanumeric() && !punctuation.contains(c))
+    {
+        anyhow::bail!("Unexpected character, '{c}'");
+    }
+
+    Ok(())
+}
+
+/// Get the package name and version from something like
+/// `wapm:///hotg-ai/argmax?version=0.12.0`, `wapm:///hotg-ai/argmax@0.12` or
+/// `wapm://hotg-ai/argmax`.
+pub(super) fn wapm_package(uri: &URI<'_>) -> Result<(String, Option<String>), Error> {
+    let mut segments: Vec<String> = Vec::new();
+
+    if let Some(host) = uri.host() {
+        let host = host.to_string();
+        if !host.is_empty() {
+            segments.push(host);
+        }
+    }
+    segments.extend(
+        uri.path()
+            .segments()
+            .iter()
+            .map(|s| s.as_str().to_string())
+            .filter(|s| !s.is_empty()),
+    );
+
+    if segments.is_empty() {
+        anyhow::bail!("\"{uri}\" doesn't specify a package");
+    }
+
+    let mut name = segments.join("/");
+    let mut version = None;
+
+    if let Some((package, v)) = name.split_once('@') {
+        version = Some(v.to_string());
+        name = package.to_string();
+    }
+
+    if let Some(query) = uri.query() {
+        let query = queryst::parse(query.as_str())
+            .map_err(|e| Error::msg(format!("Unable to parse the query string: {e:?}")))?;
+
+        if let Some(v) = query.get("version").and_then(|v| v.as_str()) {
+            version = Some(v.to_string());
+        }
+    }
+
+    Ok((name, version))
+}
+
+/// Find where a package was saved, using the highest version in the cache if
+/// no `version` is specified.
+pub(crate) fn find_file_location(
+    conn: &Connection,
+    name: &str,
+    version: Option<&str>,
+) -> Result<Option<String>, Error> {
+    let found = find_version(conn, name, version)?;
+    Ok(found.map(|(_, file_loc)| file_loc))
+}
+
+/// Find the cached version of a package which best matches `version`,
+/// returning the full version and where it was saved.
+///
+/// An exact match always wins. Otherwise, partial versions (e.g. `0.12`)
+/// match the highest version with the same leading components (so `0.12`
+/// picks `0.12.10` over `0.12.2`), with pre-releases only being used when
+/// nothing else matches.
+pub(crate) fn find_version(
+    conn: &Connection,
+    name: &str,
+    version: Option<&str>,
+) -> Result<Option<(String, String)>, Error> {
+    let mut stmt = conn
+        .prepare("SELECT version, fileLoc FROM proc_blocks WHERE name = ?")
+        .context("Unable to query the proc-block cache")?;
+    let candidates = stmt
+        .query_map(params![name], |row| Ok((row.get(0)?, row.get(1)?)))
+        .context("Unable to query the proc-block cache")?
+        .collect::<Result<Vec<(String, String)>, _>>()
+        .context("Unable to read the proc-block cache")?;
+
+    Ok(best_match(candidates, version))
+}
+
+fn best_match(
+    candidates: Vec<(String, String)>,
+    version: Option<&str>,
+) -> Option<(String, String)> {
+    if let Some(version) = version {
+        if let Some(exact) = candidates.iter().find(|(v, _)| v == version) {
+            return Some(exact.clone());
+        }
+    }
+
+    let prefix = match version {
+        Some(version) => Some(partial_version(version)?),
+        None => None,
+    };
+
+    candidates
+        .into_iter()
+        .filter_map(|(v, file_loc)| {
+            let parsed = Version::parse(&v).ok()?;
+            Some((parsed, v, file_loc))
+        })
+        .filter(|(parsed, _, _)| prefix.as_ref().map_or(true, |p| p.matches(parsed)))
+        .max_by(|(a, _, _), (b, _, _)| {
+            a.pre
+                .is_empty()
+                .cmp(&b.pre.is_empty())
+                .then_with(|| a.cmp(b))
+        })
+        .map(|(_, v, file_loc)| (v, file_loc))
+}
+
+/// The leading components of a version, e.g. `0.12` or `1`.
+struct PartialVersion(Vec<u64>);
+
+impl PartialVersion {
+    fn matches(&self, version: &Version) -> bool {
+        let components = [version.major, version.minor, version.patch];
+        self.0.iter().zip(components).all(|(&a, b)| a == b)
+    }
+}
+
+fn partial_version(version: &str) -> Option<PartialVersion> {
+    let components = version
+        .split('.')
+        .map(|c| c.parse().ok())
+        .collect::<Option<Vec<u64>>>()?;
+
+    if components.is_empty() || components.len() > 3 {
+        return None;
+    }
+
+    Some(PartialVersion(components))
+}
+
+/// Find out how often the compiler was able to use the proc-block cache.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub fn asset_cache_stats(loader: tauri::State<'_, Arc<LocalAssetLoader>>) -> AssetCacheStats {
+    loader.stats()
+}
+
+/// Control whether the compiler may download proc-blocks that aren't in the
+/// local cache.
+#[tauri::command]
+#[tracing::instrument(skip(loader))]
+pub fn set_allow_network(
+    loader: tauri::State<'_, Arc<LocalAssetLoader>>,
+    allow_network: bool,
+) -> AssetCacheStats {
+    loader.set_allow_network(allow_network);
+    loader.stats()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_scheme_less_references() {
+        assert_eq!(
+            parse_package("hotg-ai/argmax@0.12"),
+            Some(("hotg-ai/argmax".to_string(), Some("0.12".to_string())))
+        );
+        assert_eq!(
+            parse_package("hotg-ai/argmax"),
+            Some(("hotg-ai/argmax".to_string(), None))
+        );
+        assert_eq!(parse_package("./model.tflite"), None);
+        assert_eq!(parse_package("a/b/c@1.0"), None);
+        assert_eq!(parse_package("argmax@1.0"), None);
+        assert_eq!(
+            parse_package("hotg-ai/argmax@1.0.0+local"),
+            Some((
+                "hotg-ai/argmax".to_string(),
+                Some("1.0.0+local".to_string())
+            ))
+        );
+    }
+
+    #[test]
+    fn partial_versions_match_the_highest_version() {
+        let candidates: Vec<(String, String)> =
+            ["0.12.2", "0.12.10", "0.13.0", "0.12.11-rc.1", "1.0.0"]
+                .iter()
+                .map(|v| (v.to_string(), format!("{v}/pb.wasm")))
+                .collect();
+        let best = |version| best_match(candidates.clone(), version).map(|(v, _)| v);
+
+        assert_eq!(best(Some("0.12")).as_deref(), Some("0.12.10"));
+        assert_eq!(best(Some("0")).as_deref(), Some("0.13.0"));
+        assert_eq!(best(Some("0.12.2")).as_deref(), Some("0.12.2"));
+        assert_eq!(best(Some("0.12.11-rc.1")).as_deref(), Some("0.12.11-rc.1"));
+        assert_eq!(best(None).as_deref(), Some("1.0.0"));
+        assert_eq!(best(Some("0.1_")), None);
+        assert_eq!(best(Some("0.14")), None);
+    }
+
+    #[test]
+    fn valid_package_names() {
+        let names = ["hotg-ai/argmax", "my_team/f1-score"];
+
+        for name in names {
+            assert!(validate_package_name(name).is_ok(), "{name}");
+            assert!(parse_package(name).is_some(), "{name}");
+        }
+    }
+
+    #[test]
+    fn names_cant_escape_the_cache() {
+        let names = [
+            "",
+            "argmax",
+            "../argmax",
+            "hotg-ai/..",
+            "/etc/passwd",
+            "a/b/c",
+            "a\\b",
+            "hotg-ai/arg+max",
+        ];
+
+        for name in names {
+            assert!(validate_package_name(name).is_err(), "{name}");
+            assert!(parse_package(name).is_none(), "{name}");
+        }
+    }
+
+    #[test]
+    fn resolve_scheme_less_references_the_compiler_turned_into_paths() {
+        let uri = URI::try_from("file:///does/not/exist/hotg-ai/argmax@0.12").unwrap();
+
+        assert_eq!(
+            package_reference(&uri),
+            Some(("hotg-ai/argmax".to_string(), Some("0.12".to_string())))
+        );
+    }
+
+    #[test]
+    fn wapm_uris_can_use_an_at_for_the_version() {
+        let uri = URI::try_from("wapm:///hotg-ai/argmax@0.12").unwrap();
+
+        assert_eq!(
+            wapm_package(&uri).unwrap(),
+            ("hotg-ai/argmax".to_string(), Some("0.12".to_string()))
+        );
+    }
+}
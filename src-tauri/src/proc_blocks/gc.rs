@@ -0,0 +1,371 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Error};
+use duckdb::{params, Connection, OptionalExt};
+use uriparse::URI;
+
+use crate::{
+    compiler::Runefile,
+    proc_blocks::asset_loader::{
+        find_version, parse_package, validate_package_name, validate_version, wapm_package,
+    },
+    projects::{saved_pipelines, saved_runefiles},
+    shared::{GarbageCollection, NodeKind, ProcBlockDiskUsage, SerializableError},
+    AppState,
+};
+
+/// Remove a particular version of a proc-block from the local cache.
+#[tauri::command]
+#[tracing::instrument(skip(app), err)]
+pub async fn uninstall_proc_block(
+    app: tauri::State<'_, AppState>,
+    name: String,
+    version: String,
+) -> Result<ProcBlockDiskUsage, SerializableError> {
+    let conn = app.meta_db().await;
+
+    let entry = entries(&conn, app.home_dir())?
+        .into_iter()
+        .find(|e| e.usage.name == name && e.usage.version == version)
+        .with_context(|| format!("{name}@{version} isn't installed"))?;
+
+    remove(&conn, &entry)?;
+
+    Ok(entry.usage)
+}
+
+/// Show how much space each version of each proc-block takes up on disk.
+#[tauri::command]
+#[tracing::instrument(skip(app), err)]
+pub async fn proc_block_disk_usage(
+    app: tauri::State<'_, AppState>,
+) -> Result<Vec<ProcBlockDiskUsage>, SerializableError> {
+    let conn = app.meta_db().await;
+    let usage = entries(&conn, app.home_dir())?
+        .into_iter()
+        .map(|e| e.usage)
+        .collect();
+
+    Ok(usage)
+}
+
+/// Delete every proc-block that isn't used by a project, either through the
+/// last Runefile it compiled or its saved pipeline (see
+/// [`crate::projects::save_pipeline`]).
+///
+/// Set `dry_run` to see what would be removed without deleting anything.
+#[tauri::command]
+#[tracing::instrument(skip(app), err)]
+pub async fn collect_proc_block_garbage(
+    app: tauri::State<'_, AppState>,
+    dry_run: bool,
+) -> Result<GarbageCollection, SerializableError> {
+    let conn = app.meta_db().await;
+    let mut referenced = HashSet::new();
+
+    for identifier in references(&conn)? {
+        let identifier = identifier.as_str();
+
+        match resolve_identifier(&conn, identifier) {
+            Ok(Some(package)) => {
+                referenced.insert(package);
+            }
+            Ok(None) => {
+                tracing::debug!(identifier, "Pipeline references an unknown proc-block");
+            }
+            Err(e) => {
+                tracing::warn!(
+                    error = &*e as &dyn std::error::Error,
+                    identifier,
+                    "Unable to resolve a proc-block reference",
+                );
+            }
+        }
+    }
+
+    let mut removed = Vec::new();
+
+    for entry in entries(&conn, app.home_dir())? {
+        let key = (entry.usage.name.clone(), entry.usage.version.clone());
+
+        if referenced.contains(&key) {
+            continue;
+        }
+
+        if !dry_run {
+            remove(&conn, &entry)?;
+        }
+
+        removed.push(entry.usage);
+    }
+
+    let bytes_freed = removed.iter().map(|u| u.bytes).sum();
+
+    tracing::info!(
+        removed = removed.len(),
+        bytes_freed,
+        dry_run,
+        "Collected proc-block garbage"
+    );
+
+    Ok(GarbageCollection {
+        removed,
+        bytes_freed,
+        dry_run,
+    })
+}
+
+/// Every proc-block identifier used by a project.
+fn references(conn: &Connection) -> Result<Vec<String>, Error> {
+    let mut identifiers = Vec::new();
+
+    for (project, src) in saved_runefiles(conn)? {
+        match Runefile::parse(&src) {
+            Ok(runefile) => identifiers.extend(
+                runefile
+                    .pipeline
+                    .into_values()
+                    .filter_map(|stage| stage.proc_block),
+            ),
+            Err(e) => {
+                tracing::warn!(
+                    error = &e as &dyn std::error::Error,
+                    %project,
+                    "Skipping a malformed saved Runefile",
+                );
+            }
+        }
+    }
+
+    identifiers.extend(
+        saved_pipelines(conn)?
+            .into_iter()
+            .flat_map(|(_, p)| p.0.into_values())
+            .filter(|node| node.ty == NodeKind::ProcBlock)
+            .map(|node| node.identifier),
+    );
+
+    Ok(identifiers)
+}
+
+/// A row in the `proc_blocks` table.
+struct Entry {
+    usage: ProcBlockDiskUsage,
+    /// The directory the package was saved to, if it is safe to delete.
+    dir: Option<PathBuf>,
+}
+
+fn entries(conn: &Connection, home_dir: &Path) -> Result<Vec<Entry>, Error> {
+    let mut stmt = conn
+        .prepare("SELECT name, version, local, fileLoc FROM proc_blocks ORDER BY name, createdAt")
+        .context("Unable to query the proc-block cache")?;
+
+    let rows = stmt
+        .query_map(params![], |row| {
+            Ok((
+                row.get(0)?,
+                row.get(1)?,
+                row.get::<_, Option<bool>>(2)?,
+                row.get::<_, Option<String>>(3)?,
+            ))
+        })
+        .context("Unable to query the proc-block cache")?;
+
+    let mut entries = Vec::new();
+
+    for row in rows {
+        let (name, version, local, file_loc): (String, String, _, _) = row?;
+
+        let dir = package_dir(home_dir, &name, &version, file_loc.as_deref());
+        let bytes = match &dir {
+            Some(dir) => dir_size(dir)
+                .with_context(|| format!("Unable to check the size of \"{}\"", dir.display()))?,
+            None => 0,
+        };
+
+        entries.push(Entry {
+            usage: ProcBlockDiskUsage {
+                name,
+                version,
+                local: local.unwrap_or(false),
+                bytes,
+            },
+            dir,
+        });
+    }
+
+    Ok(entries)
+}
+
+fn remove(conn: &Connection, entry: &Entry) -> Result<(), Error> {
+    let ProcBlockDiskUsage { name, version, .. } = &entry.usage;
+
+    if let Some(dir) = &entry.dir {
+        if dir.exists() {
+            std::fs::remove_dir_all(dir)
+                .with_context(|| format!("Unable to delete \"{}\"", dir.display()))?;
+        }
+
+        // Clean up the package's directory if that was the last version
+        if let Some(parent) = dir.parent() {
+            if parent
+                .read_dir()
+                .map(|mut d| d.next().is_none())
+                .unwrap_or(false)
+            {
+                let _ = std::fs::remove_dir(parent);
+            }
+        }
+    }
+
+    for table in ["proc_blocks", "proc_block_signatures"] {
+        conn.execute(
+            &format!("DELETE FROM {table} WHERE name = ? AND version = ?"),
+            params![name, version],
+        )
+        .with_context(|| format!("Unable to remove {name}@{version} from the {table} table"))?;
+    }
+
+    tracing::info!(%name, %version, bytes = entry.usage.bytes, "Removed a proc-block");
+
+    Ok(())
+}
+
+/// Where a package was saved in the proc-block cache.
+///
+/// The name and version are validated first so a malformed row can't be used
+/// to delete something outside the cache. Rows which predate the current
+/// naming rules fall back to the directory containing their `fileLoc`, as long
+/// as it is inside the cache. Otherwise there's nothing we can safely delete
+/// and only the row itself gets removed.
+fn package_dir(
+    home_dir: &Path,
+    name: &str,
+    version: &str,
+    file_loc: Option<&str>,
+) -> Option<PathBuf> {
+    let cache_dir = home_dir.join("proc_blocks");
+
+    match validate_package_name(name).and_then(|_| validate_version(version)) {
+        Ok(_) => Some(cache_dir.join(name).join(version)),
+        Err(e) => {
+            tracing::warn!(
+                error = &*e as &dyn std::error::Error,
+                %name,
+                %version,
+                "Found a malformed proc-block cache entry",
+            );
+
+            let dir = Path::new(file_loc?).parent()?.canonicalize().ok()?;
+            let cache_dir = cache_dir.canonicalize().ok()?;
+
+            if dir.starts_with(&cache_dir) && dir != cache_dir {
+                Some(dir)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+/// Figure out which package a pipeline node's identifier refers to.
+///
+/// Identifiers are normally the cached `fileLoc` or the package's public URL,
+/// but `wapm://` URIs and scheme-less references like `hotg-ai/argmax@0.12`
+/// are also accepted.
+pub(crate) fn resolve_identifier(
+    conn: &Connection,
+    identifier: &str,
+) -> Result<Option<(String, String)>, Error> {
+    let found = conn
+        .query_row(
+            "SELECT name, version FROM proc_blocks WHERE fileLoc = ? OR publicUrl = ? LIMIT 1",
+            params![identifier, identifier],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .context("Unable to query the proc-block cache")?;
+
+    if found.is_some() {
+        return Ok(found);
+    }
+
+    let package = match URI::try_from(identifier) {
+        Ok(uri) if uri.scheme().as_str() == "wapm" => Some(wapm_package(&uri)?),
+        Ok(_) => None,
+        Err(_) => parse_package(identifier),
+    };
+
+    let (name, version) = match package {
+        Some(package) => package,
+        None => return Ok(None),
+    };
+
+    match find_version(conn, &name, version.as_deref())? {
+        Some((version, _)) => Ok(Some((name, version))),
+        None => Ok(version.map(|version| (name, version))),
+    }
+}
+
+fn dir_size(path: &Path) -> Result<u64, std::io::Error> {
+    if !path.exists() {
+        return Ok(0);
+    }
+
+    let mut total = 0;
+
+    for entry in std::fs::read_dir(path)? {
+        let entry = entry?;
+        let meta = entry.metadata()?;
+
+        if meta.is_dir() {
+            total += dir_size(&entry.path())?;
+        } else {
+            total += meta.len();
+        }
+    }
+
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn malformed_cache_entries_can_still_be_removed() {
+        let home_dir = std::env::temp_dir().join(format!("weld-gc-{}", uuid::Uuid::new_v4()));
+        let conn = Connection::open_in_memory().unwrap();
+        for table in ["proc_blocks", "proc_block_signatures"] {
+            conn.execute(
+                &format!("CREATE TABLE {table}(name VARCHAR, version VARCHAR, local BOOLEAN, fileLoc VARCHAR, createdAt timestamp default now())"),
+                params![],
+            )
+            .unwrap();
+        }
+        conn.execute(
+            "INSERT INTO proc_blocks(name, version, fileLoc) VALUES ('hotg-ai/argmax', '0.12.0', NULL), ('../../etc', '1.0.0', '/etc/passwd')",
+            params![],
+        )
+        .unwrap();
+
+        let found = entries(&conn, &home_dir).unwrap();
+
+        assert_eq!(found.len(), 2);
+        let malformed = found.iter().find(|e| e.usage.name == "../../etc").unwrap();
+        assert_eq!(malformed.dir, None);
+        assert_eq!(malformed.usage.bytes, 0);
+
+        remove(&conn, malformed).unwrap();
+
+        let remaining: Vec<String> = entries(&conn, &home_dir)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.usage.name)
+            .collect();
+        assert_eq!(remaining, ["hotg-ai/argmax"]);
+    }
+}
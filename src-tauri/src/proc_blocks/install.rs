@@ -0,0 +1,106 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Error};
+
+use crate::{
+    proc_blocks::{
+        asset_loader::{validate_package_name, validate_version},
+        introspect, is_recorded, record_package, save_signature,
+    },
+    shared::{Package, SerializableError},
+    AppState,
+};
+
+/// The namespace used for sideloaded proc-blocks which don't specify one.
+const LOCAL_NAMESPACE: &str = "local";
+
+/// Sideload a proc-block from a `*.wasm` file on disk.
+///
+/// The module is instantiated to make sure it really is a proc-block, then
+/// copied into the proc-block cache so the compiler can find it the same way
+/// it finds packages downloaded from WAPM.
+///
+/// By default, the package's name and version are taken from the proc-block's
+/// own metadata (under the `local/` namespace if it doesn't name one), but a
+/// different `name` can be provided (e.g. `"my-team/normalize"`) to avoid
+/// clashing with registry packages.
+///
+/// Installing over a version that is already in the cache is an error unless
+/// `overwrite` is set.
+#[tauri::command]
+#[tracing::instrument(skip(app), err)]
+pub async fn install_proc_block_from_file(
+    app: tauri::State<'_, AppState>,
+    path: PathBuf,
+    name: Option<String>,
+    overwrite: Option<bool>,
+) -> Result<Package, SerializableError> {
+    let wasm =
+        std::fs::read(&path).with_context(|| format!("Unable to read \"{}\"", path.display()))?;
+
+    let (mut signature, wasm) = tokio::task::spawn_blocking(move || {
+        let signature = introspect(&wasm)?;
+        Ok::<_, Error>((signature, wasm))
+    })
+    .await?
+    .with_context(|| format!("\"{}\" isn't a valid proc-block", path.display()))?;
+
+    if let Some(name) = name {
+        signature.name = name;
+    } else if !signature.name.contains('/') {
+        signature.name = format!("{LOCAL_NAMESPACE}/{}", signature.name);
+    }
+    validate_package_name(&signature.name)?;
+    validate_version(&signature.version)?;
+
+    let package = Package {
+        name: signature.name.clone(),
+        description: signature.description.clone().unwrap_or_default(),
+        last_version: signature.version.clone(),
+        public_url: format!("file://{}", path.display()),
+        local: true,
+    };
+
+    let overwrite = overwrite.unwrap_or(false);
+    let conn = app.meta_db().await;
+
+    if !overwrite && is_recorded(&conn, &package.name, &package.last_version)? {
+        return Err(Error::msg(format!(
+            "{}@{} is already installed",
+            package.name, package.last_version
+        ))
+        .into());
+    }
+
+    let file_loc = write_to_cache(app.home_dir(), &package, &wasm)?;
+    record_package(&conn, &package, &file_loc, overwrite)?;
+    save_signature(&conn, &signature)?;
+
+    tracing::info!(
+        name = %package.name,
+        version = %package.last_version,
+        file_loc = %file_loc.display(),
+        "Installed a local proc-block",
+    );
+
+    Ok(package)
+}
+
+fn write_to_cache(home_dir: &Path, package: &Package, wasm: &[u8]) -> Result<PathBuf, Error> {
+    let proc_blocks_dir = home_dir
+        .join("proc_blocks")
+        .join(&package.name)
+        .join(&package.last_version);
+    std::fs::create_dir_all(&proc_blocks_dir).with_context(|| {
+        format!(
+            "Unable to create the \"{}\" directory",
+            proc_blocks_dir.display()
+        )
+    })?;
+
+    let file_loc = proc_blocks_dir.join("pb.wasm");
+    std::fs::write(&file_loc, wasm)
+        .with_context(|| format!("Unable to write to \"{}\"", file_loc.display()))?;
+
+    Ok(file_loc)
+}
@@ -0,0 +1,306 @@
+//! Managing the proc-blocks that live in the local cache.
+
+mod asset_loader;
+mod gc;
+mod install;
+mod sync;
+
+use std::{collections::HashMap, path::Path};
+
+use anyhow::{Context, Error};
+use duckdb::{params, Connection, OptionalExt};
+use hotg_rune_runtime::zune::proc_block::{
+    ArgumentHint, ArgumentMetadata, Dimensions as RuneDimensions, ProcBlock, TensorConstraint,
+    TensorMetadata,
+};
+
+use crate::{
+    shared::{
+        ArgumentInfo, ArgumentType, Dimensions, Package, ProcBlockSignature, SerializableError,
+        TensorSignature,
+    },
+    AppState,
+};
+
+pub use self::{
+    asset_loader::{asset_cache_stats, set_allow_network, LocalAssetLoader},
+    gc::{collect_proc_block_garbage, proc_block_disk_usage, uninstall_proc_block},
+    install::install_proc_block_from_file,
+    sync::{cancel_proc_block_sync, sync_proc_blocks, SyncState},
+};
+
+pub(crate) use self::{
+    asset_loader::{find_file_location, package_reference, parse_package},
+    gc::resolve_identifier,
+    sync::{run_sync, DEFAULT_CONCURRENCY},
+};
+
+/// Find out which arguments a proc-block takes and which tensors it consumes
+/// and produces.
+///
+/// The proc-block must already be in the local cache. Signatures are
+/// remembered in `meta.db`, so we only need to instantiate the WebAssembly
+/// module the first time a particular version is inspected.
+#[tauri::command]
+#[tracing::instrument(skip(app), err)]
+pub async fn proc_block_signature(
+    app: tauri::State<'_, AppState>,
+    name: String,
+    version: String,
+) -> Result<ProcBlockSignature, SerializableError> {
+    let signature = load_signature(&app, name, version).await?;
+    Ok(signature)
+}
+
+/// Get a proc-block's signature, introspecting it if it isn't already in the
+/// signature cache.
+pub(crate) async fn load_signature(
+    app: &AppState,
+    name: String,
+    version: String,
+) -> Result<ProcBlockSignature, Error> {
+    let file_loc = {
+        let conn = app.meta_db().await;
+
+        if let Some(signature) = cached_signature(&conn, &name, &version)? {
+            return Ok(signature);
+        }
+
+        file_location(&conn, &name, &version)?
+    };
+
+    let wasm =
+        std::fs::read(&file_loc).with_context(|| format!("Unable to read \"{file_loc}\""))?;
+
+    let mut signature = tokio::task::spawn_blocking(move || introspect(&wasm)).await??;
+    // Registry packages are namespaced, so their names won't necessarily
+    // match what the proc-block calls itself.
+    signature.name = name;
+    signature.version = version;
+
+    let conn = app.meta_db().await;
+    save_signature(&conn, &signature)?;
+
+    Ok(signature)
+}
+
+/// Look up where a proc-block's `*.wasm` file was saved.
+pub(crate) fn file_location(conn: &Connection, name: &str, version: &str) -> Result<String, Error> {
+    find_file_location(conn, name, Some(version))?
+        .with_context(|| format!("{name}@{version} isn't in the proc-block cache"))
+}
+
+/// Is there an entry for this exact version in the `proc_blocks` table?
+pub(crate) fn is_recorded(conn: &Connection, name: &str, version: &str) -> Result<bool, Error> {
+    let count: usize = conn
+        .query_row(
+            "SELECT count(*) FROM proc_blocks WHERE name = ? AND version = ?",
+            params![name, version],
+            |row| row.get(0),
+        )
+        .context("Unable to query the proc-block cache")?;
+
+    Ok(count > 0)
+}
+
+/// Add a package to the `proc_blocks` table.
+///
+/// It is an error if there is already an entry for the same version, unless
+/// `overwrite` is set, in which case the existing entry is replaced.
+pub(crate) fn record_package(
+    conn: &Connection,
+    package: &Package,
+    file_loc: &Path,
+    overwrite: bool,
+) -> Result<(), Error> {
+    if is_recorded(conn, &package.name, &package.last_version)? {
+        if !overwrite {
+            anyhow::bail!(
+                "{}@{} is already in the proc-block cache",
+                package.name,
+                package.last_version
+            );
+        }
+
+        conn.execute(
+            "DELETE FROM proc_blocks WHERE name = ? AND version = ?",
+            params![&package.name, &package.last_version],
+        )
+        .context("Unable to remove the previous entry")?;
+    }
+
+    conn.execute(
+        "INSERT INTO proc_blocks (name, version, publicUrl, description, fileLoc, local, createdAt) VALUES (?, ?, ?, ?, ?, ?, now())",
+        params![
+            &package.name,
+            &package.last_version,
+            &package.public_url,
+            &package.description,
+            file_loc.to_str(),
+            package.local,
+        ],
+    )
+    .with_context(|| format!("Unable to record {}@{}", package.name, package.last_version))?;
+
+    Ok(())
+}
+
+fn cached_signature(
+    conn: &Connection,
+    name: &str,
+    version: &str,
+) -> Result<Option<ProcBlockSignature>, Error> {
+    let json: Option<String> = conn
+        .query_row(
+            "SELECT signature FROM proc_block_signatures WHERE name = ? AND version = ?",
+            params![name, version],
+            |row| row.get(0),
+        )
+        .optional()
+        .context("Unable to query the signature cache")?;
+
+    match json {
+        Some(json) => match serde_json::from_str(&json) {
+            Ok(signature) => Ok(Some(signature)),
+            Err(e) => {
+                // The format probably changed between releases. Just
+                // introspect the proc-block again.
+                tracing::warn!(
+                    error = &e as &dyn std::error::Error,
+                    name,
+                    version,
+                    "Ignoring a malformed cached signature",
+                );
+                Ok(None)
+            }
+        },
+        None => Ok(None),
+    }
+}
+
+pub(crate) fn save_signature(
+    conn: &Connection,
+    signature: &ProcBlockSignature,
+) -> Result<(), Error> {
+    let json = serde_json::to_string(signature)?;
+
+    conn.execute(
+        "DELETE FROM proc_block_signatures WHERE name = ? AND version = ?",
+        params![&signature.name, &signature.version],
+    )
+    .context("Unable to clear the old signature")?;
+    conn.execute(
+        "INSERT INTO proc_block_signatures (name, version, signature) VALUES (?, ?, ?)",
+        params![&signature.name, &signature.version, &json],
+    )
+    .context("Unable to save the signature")?;
+
+    Ok(())
+}
+
+/// Instantiate a proc-block and ask it to describe itself.
+#[tracing::instrument(skip_all, err)]
+pub(crate) fn introspect(wasm: &[u8]) -> Result<ProcBlockSignature, Error> {
+    let mut proc_block = ProcBlock::load(wasm).context("Unable to load the proc-block")?;
+    let metadata = proc_block
+        .metadata()
+        .context("Unable to read the proc-block's metadata")?;
+
+    let arguments: Vec<ArgumentInfo> = metadata.arguments.iter().map(argument_info).collect();
+
+    // The tensors a proc-block uses often depend on its arguments, so the
+    // best we can do up front is to ask using the default values. This will
+    // fail if there are required arguments, in which case we only know the
+    // tensors' names.
+    let defaults: HashMap<String, String> = arguments
+        .iter()
+        .filter_map(|arg| Some((arg.name.clone(), arg.default_value.clone()?)))
+        .collect();
+    let graph = match proc_block.graph(&defaults) {
+        Ok(graph) => Some(graph),
+        Err(e) => {
+            tracing::debug!(
+                error = &*e as &dyn std::error::Error,
+                "Unable to determine tensor constraints using the default arguments",
+            );
+            None
+        }
+    };
+
+    let inputs = metadata
+        .inputs
+        .iter()
+        .map(|t| tensor_signature(t, graph.as_ref().and_then(|g| g.input_tensors.get(&t.name))))
+        .collect();
+    let outputs = metadata
+        .outputs
+        .iter()
+        .map(|t| {
+            tensor_signature(
+                t,
+                graph.as_ref().and_then(|g| g.output_tensors.get(&t.name)),
+            )
+        })
+        .collect();
+
+    Ok(ProcBlockSignature {
+        name: metadata.name.clone(),
+        version: metadata.version.clone(),
+        description: metadata.description.clone(),
+        arguments,
+        inputs,
+        outputs,
+    })
+}
+
+fn argument_info(arg: &ArgumentMetadata) -> ArgumentInfo {
+    let mut ty = ArgumentType::Unknown;
+
+    for hint in &arg.hints {
+        match hint {
+            ArgumentHint::OneOf(items) => ty = ArgumentType::OneOf(items.clone()),
+            ArgumentHint::ArgumentType(t) if ty == ArgumentType::Unknown => {
+                use hotg_rune_runtime::zune::proc_block::ArgumentType as A;
+
+                ty = match t {
+                    A::Integer => ArgumentType::Integer,
+                    A::UnsignedInteger => ArgumentType::UnsignedInteger,
+                    A::Float => ArgumentType::Float,
+                    A::String => ArgumentType::String,
+                    A::LongString => ArgumentType::LongString,
+                };
+            }
+            _ => {}
+        }
+    }
+
+    ArgumentInfo {
+        name: arg.name.clone(),
+        description: arg.description.clone(),
+        ty,
+        default_value: arg.default_value.clone(),
+    }
+}
+
+fn tensor_signature(
+    metadata: &TensorMetadata,
+    constraint: Option<&TensorConstraint>,
+) -> TensorSignature {
+    let element_types = constraint
+        .map(|c| c.element_types.iter().copied().map(Into::into).collect())
+        .unwrap_or_default();
+    let dimensions = match constraint.map(|c| &c.dimensions) {
+        Some(RuneDimensions::Fixed(dims)) => {
+            let dims: Vec<usize> = dims.iter().map(|&d| d as usize).collect();
+            Dimensions::with_wildcards(&dims)
+        }
+        Some(RuneDimensions::Dynamic) | None => Dimensions::Dynamic,
+    };
+
+    TensorSignature {
+        name: metadata.name.clone(),
+        description: metadata.description.clone(),
+        element_types,
+        dimensions,
+    }
+}
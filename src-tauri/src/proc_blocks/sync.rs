@@ -0,0 +1,345 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    fs::{File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Error};
+use futures::stream::StreamExt;
+use reqwest::{header, StatusCode};
+
+use crate::{
+    proc_blocks::{find_file_location, record_package},
+    shared::{Package, ProcBlockSyncEvent, SerializableError, SyncSummary},
+    wapm::fetch_packages,
+    AppState,
+};
+
+/// How many packages are downloaded at the same time when the caller doesn't
+/// say otherwise.
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+/// Don't flood the frontend with progress events.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Keeps track of the proc-block sync that is currently running, if any.
+#[derive(Debug, Default)]
+pub struct SyncState {
+    running: AtomicBool,
+    cancelled: AtomicBool,
+}
+
+impl SyncState {
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Download any proc-blocks from WAPM that aren't already in the local cache.
+///
+/// Progress is reported using `proc_block_sync_progress` events. Partially
+/// downloaded files are kept around when a download fails or is cancelled,
+/// and the next sync will try to resume from where it left off.
+#[tauri::command]
+#[tracing::instrument(skip(app, client, sync, window), err)]
+pub async fn sync_proc_blocks(
+    app: tauri::State<'_, AppState>,
+    client: tauri::State<'_, reqwest::Client>,
+    sync: tauri::State<'_, SyncState>,
+    window: tauri::Window,
+    concurrency: Option<usize>,
+) -> Result<SyncSummary, SerializableError> {
+    let concurrency = concurrency.unwrap_or(DEFAULT_CONCURRENCY);
+
+    let summary = run_sync(&app, &client, &sync, concurrency, |event| {
+        if let Err(e) = window.emit("proc_block_sync_progress", &event) {
+            tracing::warn!(
+                error = &e as &dyn std::error::Error,
+                "Unable to emit a sync event",
+            );
+        }
+    })
+    .await?;
+
+    Ok(summary)
+}
+
+/// Stop the proc-block sync that is currently running.
+///
+/// Returns `false` if there was nothing to cancel.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub fn cancel_proc_block_sync(sync: tauri::State<'_, SyncState>) -> bool {
+    let running = sync.running.load(Ordering::Relaxed);
+
+    if running {
+        sync.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    running
+}
+
+pub(crate) async fn run_sync(
+    state: &AppState,
+    client: &reqwest::Client,
+    sync: &SyncState,
+    concurrency: usize,
+    on_event: impl Fn(ProcBlockSyncEvent),
+) -> Result<SyncSummary, Error> {
+    if sync.running.swap(true, Ordering::SeqCst) {
+        anyhow::bail!("The proc-blocks are already being synced");
+    }
+    sync.cancelled.store(false, Ordering::SeqCst);
+    let _guard = ResetOnDrop(&sync.running);
+
+    let packages = fetch_packages(client).await?;
+    let packages = missing_packages(state, packages).await?;
+
+    tracing::info!(packages_to_download = ?packages, "Syncing proc-blocks");
+    on_event(ProcBlockSyncEvent::Started {
+        packages: packages.clone(),
+    });
+
+    let home_dir = state.home_dir();
+    let on_event = &on_event;
+
+    let mut downloads = futures::stream::iter(packages)
+        .map(|package| async move {
+            // Downloads are queued, so the sync may have been cancelled
+            // while this one was waiting its turn
+            if sync.is_cancelled() {
+                return (package, Err(SyncCancelled.into()));
+            }
+
+            let dir = home_dir
+                .join("proc_blocks")
+                .join(&package.name)
+                .join(&package.last_version);
+            let result = download(client, &dir, &package, sync, on_event).await;
+            (package, result)
+        })
+        .buffer_unordered(concurrency.max(1));
+
+    let mut summary = SyncSummary::default();
+
+    while let Some((package, result)) = downloads.next().await {
+        let Package {
+            name,
+            last_version: version,
+            ..
+        } = &package;
+
+        let result = match result {
+            Ok(file_loc) => {
+                let conn = state.meta_db().await;
+                record_package(&conn, &package, &file_loc, false)
+            }
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Ok(_) => {
+                tracing::info!(%name, %version, "Downloaded a proc-block");
+                summary.downloaded += 1;
+                on_event(ProcBlockSyncEvent::Finished {
+                    name: name.clone(),
+                    version: version.clone(),
+                });
+            }
+            Err(e) if e.is::<SyncCancelled>() => {
+                summary.cancelled = true;
+            }
+            Err(e) => {
+                tracing::warn!(
+                    error = &*e as &dyn std::error::Error,
+                    %name,
+                    %version,
+                    "Unable to download a proc-block",
+                );
+                summary.failed += 1;
+                on_event(ProcBlockSyncEvent::Failed {
+                    name: name.clone(),
+                    version: version.clone(),
+                    error: format!("{e:#}"),
+                });
+            }
+        }
+    }
+
+    summary.cancelled |= sync.is_cancelled();
+    on_event(ProcBlockSyncEvent::Completed(summary.clone()));
+
+    Ok(summary)
+}
+
+async fn missing_packages(state: &AppState, packages: Vec<Package>) -> Result<Vec<Package>, Error> {
+    let conn = state.meta_db().await;
+    let mut missing = Vec::new();
+
+    for package in packages {
+        if find_file_location(&conn, &package.name, Some(&package.last_version))?.is_none() {
+            tracing::debug!(name = %package.name, version = %package.last_version, "No record of package");
+            missing.push(package);
+        }
+    }
+
+    Ok(missing)
+}
+
+/// Download a package into its directory in the proc-block cache, resuming a
+/// previous download if possible.
+///
+/// The `ETag` (or `Last-Modified` date) of a download is saved next to the
+/// partial file and sent back as `If-Range` when resuming, so the server will
+/// send the whole file again (with a `200 OK`) if it has changed in the
+/// meantime. Partial downloads without a validator always start from scratch.
+#[tracing::instrument(skip_all, fields(name = %package.name, version = %package.last_version))]
+async fn download(
+    client: &reqwest::Client,
+    dir: &Path,
+    package: &Package,
+    sync: &SyncState,
+    on_event: &impl Fn(ProcBlockSyncEvent),
+) -> Result<PathBuf, Error> {
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Unable to create the \"{}\" directory", dir.display()))?;
+
+    let partial = dir.join("pb.wasm.part");
+    let validator_file = dir.join("pb.wasm.part.etag");
+    let file_loc = dir.join("pb.wasm");
+
+    let validator = std::fs::read_to_string(&validator_file).ok();
+    let mut already_downloaded = match &validator {
+        Some(_) => std::fs::metadata(&partial).map(|m| m.len()).unwrap_or(0),
+        None => 0,
+    };
+
+    let mut response = loop {
+        let mut request = client.get(&package.public_url);
+        if let Some(validator) = validator.as_deref().filter(|_| already_downloaded > 0) {
+            request = request
+                .header(header::RANGE, format!("bytes={already_downloaded}-"))
+                .header(header::IF_RANGE, validator);
+        }
+
+        let response = request
+            .send()
+            .await
+            .with_context(|| format!("Unable to fetch \"{}\"", package.public_url))?;
+
+        if response.status() == StatusCode::RANGE_NOT_SATISFIABLE && already_downloaded > 0 {
+            // The partial file doesn't match what's on the server any more,
+            // so start again from scratch.
+            tracing::debug!("Discarding a stale partial download");
+            already_downloaded = 0;
+            continue;
+        }
+
+        break response.error_for_status()?;
+    };
+
+    let (mut file, mut downloaded) =
+        if already_downloaded > 0 && response.status() == StatusCode::PARTIAL_CONTENT {
+            tracing::debug!(already_downloaded, "Resuming a partial download");
+            let file = OpenOptions::new()
+                .append(true)
+                .open(&partial)
+                .with_context(|| format!("Unable to open \"{}\"", partial.display()))?;
+            (file, already_downloaded)
+        } else {
+            if already_downloaded > 0 {
+                tracing::debug!("The file changed since the last attempt, starting again");
+            }
+            let file = File::create(&partial)
+                .with_context(|| format!("Unable to create \"{}\"", partial.display()))?;
+            save_validator(&validator_file, response.headers());
+            (file, 0)
+        };
+
+    let total_bytes = response.content_length().map(|len| len + downloaded);
+    let progress = |downloaded_bytes| ProcBlockSyncEvent::Progress {
+        name: package.name.clone(),
+        version: package.last_version.clone(),
+        downloaded_bytes,
+        total_bytes,
+    };
+
+    on_event(progress(downloaded));
+    let mut last_update = Instant::now();
+
+    while let Some(chunk) = response.chunk().await? {
+        if sync.is_cancelled() {
+            return Err(SyncCancelled.into());
+        }
+
+        file.write_all(&chunk)
+            .with_context(|| format!("Unable to write to \"{}\"", partial.display()))?;
+        downloaded += chunk.len() as u64;
+
+        if last_update.elapsed() >= PROGRESS_INTERVAL {
+            on_event(progress(downloaded));
+            last_update = Instant::now();
+        }
+    }
+
+    file.flush()?;
+    drop(file);
+    on_event(progress(downloaded));
+
+    std::fs::rename(&partial, &file_loc).with_context(|| {
+        format!(
+            "Unable to move \"{}\" to \"{}\"",
+            partial.display(),
+            file_loc.display()
+        )
+    })?;
+    let _ = std::fs::remove_file(&validator_file);
+
+    Ok(file_loc)
+}
+
+/// Remember what the server called this version of the file so a later
+/// attempt can check it hasn't changed before resuming.
+fn save_validator(path: &Path, headers: &header::HeaderMap) {
+    let get = |name: header::HeaderName| headers.get(name).and_then(|value| value.to_str().ok());
+    // Weak ETags can't be used with If-Range
+    let etag = get(header::ETAG).filter(|etag| !etag.starts_with("W/"));
+
+    let result = match etag.or_else(|| get(header::LAST_MODIFIED)) {
+        Some(validator) => std::fs::write(path, validator),
+        None => match std::fs::remove_file(path) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            other => other,
+        },
+    };
+
+    if let Err(e) = result {
+        tracing::warn!(
+            error = &e as &dyn std::error::Error,
+            path = %path.display(),
+            "Unable to save the download's ETag",
+        );
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct SyncCancelled;
+
+impl Display for SyncCancelled {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "The sync was cancelled")
+    }
+}
+
+impl std::error::Error for SyncCancelled {}
+
+struct ResetOnDrop<'a>(&'a AtomicBool);
+
+impl Drop for ResetOnDrop<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,271 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Error};
+use duckdb::{params, Connection, OptionalExt};
+use hotg_rune_compiler::{BuildConfig, FeatureFlags};
+use ts_rs::TS;
+
+use crate::{
+    shared::{Pipeline, SerializableError},
+    AppState,
+};
+
+/// Get a project's settings, falling back to the defaults if they haven't
+/// been saved yet.
+#[tauri::command]
+#[tracing::instrument(skip(app), err)]
+pub async fn get_project_settings(
+    app: tauri::State<'_, AppState>,
+    name: String,
+) -> Result<ProjectSettings, SerializableError> {
+    let conn = app.meta_db().await;
+    let settings = load_settings(&conn, app.home_dir(), &name)?;
+
+    Ok(settings)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip(app), err)]
+pub async fn save_project_settings(
+    app: tauri::State<'_, AppState>,
+    name: String,
+    settings: ProjectSettings,
+) -> Result<(), SerializableError> {
+    validate_name(&name)?;
+    // Make sure we'd actually be able to compile with these settings
+    settings.build_config()?;
+
+    let conn = app.meta_db().await;
+
+    conn.execute("DELETE FROM projects WHERE name = ?", params![name])
+        .context("Unable to update the project settings")?;
+    conn.execute(
+        "INSERT INTO projects(name, directory, unstableFeatures) VALUES (?, ?, ?)",
+        params![
+            name,
+            settings.directory.display().to_string(),
+            settings.unstable_features
+        ],
+    )
+    .context("Unable to save the project settings")?;
+
+    Ok(())
+}
+
+/// Save the pipeline a project is working on.
+///
+/// Saved pipelines are used to figure out which proc-blocks are still needed
+/// when cleaning up the proc-block cache.
+#[tauri::command]
+#[tracing::instrument(skip(app, pipeline), err)]
+pub async fn save_pipeline(
+    app: tauri::State<'_, AppState>,
+    project: String,
+    pipeline: Pipeline,
+) -> Result<(), SerializableError> {
+    let json = serde_json::to_string(&pipeline)?;
+    let conn = app.meta_db().await;
+
+    conn.execute("DELETE FROM pipelines WHERE project = ?", params![project])
+        .context("Unable to update the saved pipeline")?;
+    conn.execute(
+        "INSERT INTO pipelines(project, pipeline) VALUES (?, ?)",
+        params![project, json],
+    )
+    .context("Unable to save the pipeline")?;
+
+    Ok(())
+}
+
+/// Get the pipeline that was saved for a project, if there is one.
+#[tauri::command]
+#[tracing::instrument(skip(app), err)]
+pub async fn get_pipeline(
+    app: tauri::State<'_, AppState>,
+    project: String,
+) -> Result<Option<Pipeline>, SerializableError> {
+    let conn = app.meta_db().await;
+
+    let json: Option<String> = conn
+        .query_row(
+            "SELECT pipeline FROM pipelines WHERE project = ?",
+            params![project],
+            |row| row.get(0),
+        )
+        .optional()
+        .context("Unable to query the saved pipelines")?;
+
+    match json {
+        Some(json) => Ok(Some(serde_json::from_str(&json).with_context(|| {
+            format!("Unable to parse the pipeline saved for \"{project}\"")
+        })?)),
+        None => Ok(None),
+    }
+}
+
+/// Remember the last Runefile that was successfully compiled for a project.
+///
+/// Like saved pipelines, these are used to figure out which proc-blocks are
+/// still needed when cleaning up the proc-block cache.
+pub(crate) fn save_runefile(conn: &Connection, project: &str, runefile: &str) -> Result<(), Error> {
+    conn.execute("DELETE FROM runefiles WHERE project = ?", params![project])
+        .context("Unable to update the saved Runefile")?;
+    conn.execute(
+        "INSERT INTO runefiles(project, runefile) VALUES (?, ?)",
+        params![project, runefile],
+    )
+    .context("Unable to save the Runefile")?;
+
+    Ok(())
+}
+
+/// The last Runefile compiled for each project.
+pub(crate) fn saved_runefiles(conn: &Connection) -> Result<Vec<(String, String)>, Error> {
+    let mut stmt = conn
+        .prepare("SELECT project, runefile FROM runefiles")
+        .context("Unable to query the saved Runefiles")?;
+    let rows = stmt
+        .query_map(params![], |row| Ok((row.get(0)?, row.get(1)?)))
+        .context("Unable to query the saved Runefiles")?;
+
+    rows.collect::<Result<_, _>>()
+        .context("Unable to read the saved Runefiles")
+}
+
+/// Every project's saved pipeline.
+///
+/// Pipelines that can't be parsed (e.g. because they were saved by an older
+/// version of Weld) are skipped.
+pub(crate) fn saved_pipelines(conn: &Connection) -> Result<Vec<(String, Pipeline)>, Error> {
+    let mut stmt = conn
+        .prepare("SELECT project, pipeline FROM pipelines")
+        .context("Unable to query the saved pipelines")?;
+    let rows = stmt
+        .query_map(params![], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })
+        .context("Unable to query the saved pipelines")?;
+
+    let mut pipelines = Vec::new();
+
+    for row in rows {
+        let (project, json) = row?;
+
+        match serde_json::from_str(&json) {
+            Ok(pipeline) => pipelines.push((project, pipeline)),
+            Err(e) => {
+                tracing::warn!(
+                    error = &e as &dyn std::error::Error,
+                    %project,
+                    "Skipping a malformed saved pipeline",
+                );
+            }
+        }
+    }
+
+    Ok(pipelines)
+}
+
+/// Project-specific settings used when compiling a Runefile.
+#[derive(Debug, Clone, PartialEq, Eq, TS, serde::Serialize, serde::Deserialize)]
+#[ts(export, export_to = "../src/backend/types/")]
+pub struct ProjectSettings {
+    /// The project's folder. Any relative paths in the Runefile (e.g. to a
+    /// model) are resolved relative to this.
+    pub directory: PathBuf,
+    /// Let the compiler use features which haven't been stabilised yet.
+    #[serde(default)]
+    pub unstable_features: bool,
+}
+
+impl ProjectSettings {
+    /// The settings a project gets when the user hasn't changed anything.
+    pub fn default_for(home_dir: &Path, name: &str) -> Result<Self, Error> {
+        validate_name(name)?;
+
+        Ok(ProjectSettings {
+            directory: home_dir.join("projects").join(name),
+            unstable_features: false,
+        })
+    }
+
+    /// Get the configuration the compiler should use for this project,
+    /// creating the project's folder if it doesn't already exist.
+    pub fn build_config(&self) -> Result<BuildConfig, Error> {
+        let ProjectSettings {
+            directory,
+            unstable_features,
+        } = self;
+
+        if !directory.is_absolute() {
+            anyhow::bail!(
+                "The project directory must be an absolute path, not \"{}\"",
+                directory.display()
+            );
+        }
+
+        std::fs::create_dir_all(directory).with_context(|| {
+            format!("Unable to create the \"{}\" directory", directory.display())
+        })?;
+
+        let features = if *unstable_features {
+            FeatureFlags::development()
+        } else {
+            FeatureFlags::stable()
+        };
+
+        Ok(BuildConfig {
+            current_directory: directory.clone(),
+            features,
+        })
+    }
+}
+
+/// Get the settings that were saved for a project, falling back to the
+/// defaults.
+///
+/// Commands which compile a project should use this instead of accepting
+/// [`ProjectSettings`] from the frontend, so the only way to point the
+/// compiler at a directory is through [`save_project_settings()`].
+pub(crate) fn load_settings(
+    conn: &Connection,
+    home_dir: &Path,
+    name: &str,
+) -> Result<ProjectSettings, Error> {
+    validate_name(name)?;
+
+    match saved_settings(conn, name)? {
+        Some(settings) => Ok(settings),
+        None => ProjectSettings::default_for(home_dir, name),
+    }
+}
+
+/// Project names are used as directory names, so make sure they can't be
+/// used to escape the projects folder.
+fn validate_name(name: &str) -> Result<(), Error> {
+    let is_valid = !name.is_empty()
+        && name != "."
+        && name != ".."
+        && !name.contains(|c| matches!(c, '/' | '\\' | ':'));
+
+    if !is_valid {
+        anyhow::bail!("\"{name}\" isn't a valid project name");
+    }
+
+    Ok(())
+}
+
+fn saved_settings(conn: &Connection, name: &str) -> Result<Option<ProjectSettings>, Error> {
+    conn.query_row(
+        "SELECT directory, unstableFeatures FROM projects WHERE name = ?",
+        params![name],
+        |row| {
+            Ok(ProjectSettings {
+                directory: PathBuf::from(row.get::<_, String>(0)?),
+                unstable_features: row.get::<_, Option<bool>>(1)?.unwrap_or(false),
+            })
+        },
+    )
+    .optional()
+    .context("Unable to query the project settings")
+}
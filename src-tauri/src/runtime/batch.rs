@@ -0,0 +1,370 @@
+//! Running a Rune over every row in a table.
+
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Error};
+use arrow::{
+    array::{Array, Int64Array},
+    record_batch::RecordBatch,
+};
+use duckdb::params;
+use ts_rs::TS;
+use uuid::Uuid;
+
+use crate::{
+    runtime::{
+        is_cancelled, load_engine, output_table::OutputTable, wait_for, ConfiguredLimits,
+        InputBinding, RunFailed, RunningAnalyses,
+    },
+    shared::{ColumnMapping, RunProfile, SerializableError, Tensor},
+    AppState, BlobStore,
+};
+
+/// Run a Rune over every row in a table, `batch_size` rows at a time.
+///
+/// Each [`ColumnMapping`] feeds the column named by its `tensor_id` into a
+/// node's input tensor, with one element per row. Progress is reported using
+/// `dataset_run_progress` events (see [`DatasetRunProgress`]), finishing with
+/// an event in one of the terminal [`RunState`]s.
+///
+/// If an `output_table` is provided, the outputs will be appended to it
+/// (creating it if necessary) with a `row_id` column containing the index of
+/// the input row each output came from.
+///
+/// The run can be stopped between batches using
+/// [`crate::runtime::cancel_analysis`]. Pass in a `run_id` to be able to
+/// cancel it before the first progress event arrives. Outputs from batches
+/// that already finished are kept.
+///
+/// The configured timeout applies to each batch.
+#[tauri::command]
+#[tracing::instrument(skip(app, blobs, limits, analyses, window, column_mappings), err)]
+pub async fn run_over_dataset(
+    app: tauri::State<'_, AppState>,
+    blobs: tauri::State<'_, BlobStore>,
+    limits: tauri::State<'_, ConfiguredLimits>,
+    analyses: tauri::State<'_, RunningAnalyses>,
+    window: tauri::Window,
+    rune: Uuid,
+    table: String,
+    column_mappings: Vec<ColumnMapping>,
+    batch_size: usize,
+    output_table: Option<String>,
+    run_id: Option<Uuid>,
+) -> Result<DatasetRunSummary, SerializableError<RunFailed>> {
+    if batch_size == 0 {
+        return Err(Error::msg("The batch size must be greater than zero").into());
+    }
+    if column_mappings.is_empty() {
+        return Err(Error::msg("At least one column needs to be mapped to a tensor").into());
+    }
+
+    let run_id = run_id.unwrap_or_else(Uuid::new_v4);
+    let registration = analyses.register(run_id)?;
+    let cancellation = registration.token();
+    let rune = blobs.get_bytes(rune)?;
+    let started = Instant::now();
+    let mut total_rows = 0;
+    let mut rows_processed = 0;
+    let mut batches = 0;
+    let mut output_table = output_table.map(OutputTable::new);
+    let mut profile = RunProfile::default();
+
+    let result: Result<(), Error> = async {
+        let mut engine = load_engine(rune, &limits, cancellation).await?;
+        let limits = limits.get();
+
+        total_rows = count_rows(&app, &table).await?;
+        let sql = select_columns(&table, &column_mappings);
+        let mut last_row_id = -1;
+        emit_progress(
+            &window,
+            DatasetRunProgress::new(run_id, RunState::Running, 0, total_rows, 0, started),
+        );
+
+        while rows_processed < total_rows {
+            cancellation.check()?;
+
+            let (batch, last) = read_batch(&app, &sql, last_row_id, batch_size).await?;
+            let rows = batch.num_rows();
+            last_row_id = match last {
+                Some(row_id) if rows > 0 => row_id,
+                _ => break,
+            };
+
+            let bindings = bindings(&batch, &column_mappings)?;
+            let worker = engine.worker();
+
+            let task = tokio::task::spawn_blocking(move || {
+                let result = engine.predict(bindings, None);
+                (engine, result)
+            });
+            let (returned, result) = cancellation
+                .guard(&worker, wait_for(task, &worker, &limits))
+                .await?;
+            engine = returned;
+
+            let mut outputs = result.with_context(|| {
+                format!(
+                    "Unable to process rows {rows_processed} to {}",
+                    rows_processed + rows
+                )
+            })?;
+            profile.merge(std::mem::take(&mut outputs.profile));
+
+            if let Some(output_table) = &mut output_table {
+                output_table
+                    .append(&app, rows_processed, rows, &outputs)
+                    .await?;
+            }
+
+            rows_processed += rows;
+            batches += 1;
+
+            emit_progress(
+                &window,
+                DatasetRunProgress::new(
+                    run_id,
+                    RunState::Running,
+                    rows_processed,
+                    total_rows,
+                    batches,
+                    started,
+                ),
+            );
+        }
+
+        Ok(())
+    }
+    .await;
+
+    let state = match &result {
+        Ok(_) => RunState::Completed,
+        Err(e) if is_cancelled(e) => RunState::Cancelled,
+        Err(_) => RunState::Failed,
+    };
+    emit_progress(
+        &window,
+        DatasetRunProgress::new(run_id, state, rows_processed, total_rows, batches, started),
+    );
+    result?;
+
+    Ok(DatasetRunSummary {
+        run_id,
+        rows_processed,
+        batches,
+        elapsed_ms: started.elapsed().as_millis() as u64,
+        output_table: output_table.as_ref().map(|t| t.name().to_string()),
+        skipped_outputs: output_table
+            .as_ref()
+            .map(OutputTable::skipped)
+            .unwrap_or_default(),
+        profile,
+    })
+}
+
+fn emit_progress(window: &tauri::Window, progress: DatasetRunProgress) {
+    if let Err(e) = window.emit("dataset_run_progress", &progress) {
+        tracing::warn!(
+            error = &e as &dyn std::error::Error,
+            "Unable to emit a progress event",
+        );
+    }
+}
+
+/// Where a run over a dataset is up to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, TS, serde::Serialize, serde::Deserialize)]
+#[ts(export, export_to = "../src/backend/types/")]
+#[serde(rename_all = "snake_case")]
+pub enum RunState {
+    Running,
+    /// Every row was processed.
+    Completed,
+    /// The run was stopped using [`crate::runtime::cancel_analysis`].
+    Cancelled,
+    /// The run stopped because of an error.
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, TS, serde::Serialize, serde::Deserialize)]
+#[ts(export, export_to = "../src/backend/types/")]
+pub struct DatasetRunProgress {
+    pub run_id: Uuid,
+    pub state: RunState,
+    pub rows_processed: usize,
+    pub total_rows: usize,
+    pub batches: usize,
+    #[ts(type = "number")]
+    pub elapsed_ms: u64,
+    /// The average number of rows processed per second so far.
+    pub rows_per_second: f64,
+    /// Roughly how long until every row has been processed, if we can tell.
+    #[ts(type = "number | null")]
+    pub eta_ms: Option<u64>,
+}
+
+impl DatasetRunProgress {
+    fn new(
+        run_id: Uuid,
+        state: RunState,
+        rows_processed: usize,
+        total_rows: usize,
+        batches: usize,
+        started: Instant,
+    ) -> Self {
+        let elapsed = started.elapsed();
+        let rows_per_second = if elapsed.is_zero() {
+            0.0
+        } else {
+            rows_processed as f64 / elapsed.as_secs_f64()
+        };
+        let eta = if rows_per_second > 0.0 {
+            let remaining = total_rows.saturating_sub(rows_processed) as f64;
+            Some(Duration::from_secs_f64(remaining / rows_per_second))
+        } else {
+            None
+        };
+
+        DatasetRunProgress {
+            run_id,
+            state,
+            rows_processed,
+            total_rows,
+            batches,
+            elapsed_ms: elapsed.as_millis() as u64,
+            rows_per_second,
+            eta_ms: eta.map(|eta| eta.as_millis() as u64),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, TS, serde::Serialize, serde::Deserialize)]
+#[ts(export, export_to = "../src/backend/types/")]
+pub struct DatasetRunSummary {
+    pub run_id: Uuid,
+    pub rows_processed: usize,
+    pub batches: usize,
+    #[ts(type = "number")]
+    pub elapsed_ms: u64,
+    /// The table outputs were saved to.
+    pub output_table: Option<String>,
+    /// Outputs which weren't saved because they didn't line up with the
+    /// input rows.
+    pub skipped_outputs: Vec<String>,
+    /// The combined profile from every batch.
+    pub profile: RunProfile,
+}
+
+async fn count_rows(app: &AppState, table: &str) -> Result<usize, Error> {
+    let db = app.db().await;
+    let sql = format!("SELECT COUNT(*) FROM {}", quote_identifier(table));
+    let count: i64 = db
+        .query_row(&sql, params![], |row| row.get(0))
+        .with_context(|| format!("Unable to count the rows in \"{table}\""))?;
+
+    Ok(count as usize)
+}
+
+/// Build a query which selects each mapped column, in order, followed by the
+/// `rowid` used to page through the table.
+///
+/// Paging by `rowid` instead of using `LIMIT`/`OFFSET` means every row is
+/// read exactly once and in a consistent order, without rescanning all the
+/// rows that came before it.
+fn select_columns(table: &str, column_mappings: &[ColumnMapping]) -> String {
+    let columns: Vec<_> = column_mappings
+        .iter()
+        .map(|m| quote_identifier(&m.tensor_id))
+        .collect();
+
+    format!(
+        "SELECT {}, rowid FROM {} WHERE rowid > ? ORDER BY rowid LIMIT ?",
+        columns.join(", "),
+        quote_identifier(table)
+    )
+}
+
+/// Read up to `limit` rows which come after `last_row_id`, returning them
+/// along with the `rowid` of the last row that was read.
+async fn read_batch(
+    app: &AppState,
+    sql: &str,
+    last_row_id: i64,
+    limit: usize,
+) -> Result<(RecordBatch, Option<i64>), Error> {
+    let db = app.db().await;
+    let mut stmt = db.prepare(sql).context("Unable to prepare the query")?;
+    let frames = stmt
+        .query_arrow(params![last_row_id, limit as i64])
+        .context("Unable to read the next batch of rows")?;
+
+    let schema = frames.get_schema();
+    let records: Vec<RecordBatch> = frames.collect();
+    let batch =
+        RecordBatch::concat(&schema, &records).context("Unable to merge the record batches")?;
+
+    let last = batch
+        .columns()
+        .last()
+        .and_then(|column| column.as_any().downcast_ref::<Int64Array>())
+        .filter(|row_ids| !row_ids.is_empty())
+        .map(|row_ids| row_ids.value(row_ids.len() - 1));
+
+    Ok((batch, last))
+}
+
+/// Turn each mapped column into the tensor for its node's input.
+///
+/// Any extra columns at the end of the batch (i.e. the `rowid`) are ignored.
+fn bindings(
+    batch: &RecordBatch,
+    column_mappings: &[ColumnMapping],
+) -> Result<Vec<InputBinding>, Error> {
+    column_mappings
+        .iter()
+        .zip(batch.columns())
+        .map(|(mapping, column)| {
+            let tensor = Tensor::from_array(column.as_ref()).with_context(|| {
+                format!("Unable to convert the \"{}\" column", mapping.tensor_id)
+            })?;
+
+            Ok(InputBinding {
+                node_name: mapping.node_name.clone(),
+                input_tensor_name: mapping.input_tensor_name.clone(),
+                tensor,
+            })
+        })
+        .collect()
+}
+
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn select_the_mapped_columns() {
+        let mappings = vec![
+            ColumnMapping {
+                tensor_id: "age".to_string(),
+                node_name: "input".to_string(),
+                input_tensor_name: "input_0".to_string(),
+            },
+            ColumnMapping {
+                tensor_id: "weird \"name\"".to_string(),
+                node_name: "input".to_string(),
+                input_tensor_name: "input_1".to_string(),
+            },
+        ];
+
+        let sql = select_columns("people", &mappings);
+
+        assert_eq!(
+            sql,
+            r#"SELECT "age", "weird ""name""", rowid FROM "people" WHERE rowid > ? ORDER BY rowid LIMIT ?"#
+        );
+    }
+}
@@ -0,0 +1,299 @@
+//! Stopping an analysis part-way through.
+//!
+//! Each run registers itself with [`RunningAnalyses`] under its run ID so the
+//! frontend can stop it using [`cancel_analysis()`]. Cancellation is
+//! cooperative - runs check their [`CancellationToken`] before each stage
+//! (loading the Rune, running a batch, etc.), and the worker running the Rune
+//! is asked to stop before it starts the pipeline. The pipeline itself can't
+//! be interrupted, so a worker that is busy running it gets killed instead
+//! (see [`CancellationToken::guard()`]).
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+    time::Duration,
+};
+
+use anyhow::Error;
+use futures::future::{self, Either};
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+use crate::runtime::{worker::Worker, RunFailed};
+
+/// How long a worker has to stop after being cancelled before it gets
+/// killed (e.g. because a node is stuck in an infinite loop).
+const GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// Cancel a running analysis, returning `true` if it was still running.
+#[tauri::command]
+#[tracing::instrument(skip(analyses))]
+pub fn cancel_analysis(analyses: tauri::State<'_, RunningAnalyses>, run_id: Uuid) -> bool {
+    analyses.cancel(run_id)
+}
+
+/// Every analysis that is currently running, keyed by run ID.
+#[derive(Debug, Default)]
+pub struct RunningAnalyses(Mutex<HashMap<Uuid, CancellationToken>>);
+
+impl RunningAnalyses {
+    /// Keep track of a run so it can be cancelled.
+    ///
+    /// The run is forgotten when the returned [`Registration`] is dropped.
+    pub(crate) fn register(&self, run_id: Uuid) -> Result<Registration<'_>, Error> {
+        let mut runs = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if runs.contains_key(&run_id) {
+            anyhow::bail!("The \"{run_id}\" run has already been started");
+        }
+
+        let token = CancellationToken::new(run_id);
+        runs.insert(run_id, token.clone());
+
+        Ok(Registration {
+            analyses: self,
+            token,
+        })
+    }
+
+    pub fn cancel(&self, run_id: Uuid) -> bool {
+        let runs = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+
+        match runs.get(&run_id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// A run that was registered with [`RunningAnalyses`].
+#[derive(Debug)]
+pub(crate) struct Registration<'a> {
+    analyses: &'a RunningAnalyses,
+    token: CancellationToken,
+}
+
+impl Registration<'_> {
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+}
+
+impl Drop for Registration<'_> {
+    fn drop(&mut self) {
+        self.analyses
+            .0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&self.token.run_id());
+    }
+}
+
+/// A flag that gets set when a run is cancelled.
+#[derive(Debug, Clone)]
+pub(crate) struct CancellationToken(Arc<Inner>);
+
+#[derive(Debug)]
+struct Inner {
+    run_id: Uuid,
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancellationToken {
+    fn new(run_id: Uuid) -> Self {
+        CancellationToken(Arc::new(Inner {
+            run_id,
+            cancelled: AtomicBool::new(false),
+            notify: Notify::new(),
+        }))
+    }
+
+    pub fn run_id(&self) -> Uuid {
+        self.0.run_id
+    }
+
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::SeqCst);
+        self.0.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Fail with [`RunFailed::Cancelled`] if the run has been cancelled.
+    pub fn check(&self) -> Result<(), RunFailed> {
+        if self.is_cancelled() {
+            Err(RunFailed::Cancelled {
+                run_id: self.run_id(),
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Wait for a stage of the run that is using a worker.
+    ///
+    /// If the run is cancelled, the worker is asked to stop and the stage
+    /// fails with [`RunFailed::Cancelled`]. Workers that don't stop within the
+    /// [`GRACE_PERIOD`] (e.g. because they are part-way through the pipeline)
+    /// are killed.
+    pub async fn guard<T>(
+        &self,
+        worker: &Worker,
+        stage: impl Future<Output = Result<T, Error>>,
+    ) -> Result<T, Error> {
+        self.stop_gracefully(
+            stage,
+            || worker.cancel(self.run_id()),
+            || worker.kill(),
+            GRACE_PERIOD,
+        )
+        .await
+    }
+
+    async fn stop_gracefully<T>(
+        &self,
+        stage: impl Future<Output = Result<T, Error>>,
+        cancel: impl FnOnce(),
+        kill: impl FnOnce(),
+        grace_period: Duration,
+    ) -> Result<T, Error> {
+        self.check()?;
+
+        let cancelled = Box::pin(self.cancelled());
+        let stage = Box::pin(stage);
+
+        let stage = match future::select(stage, cancelled).await {
+            Either::Left((result, _)) => return result,
+            Either::Right(((), stage)) => stage,
+        };
+
+        cancel();
+
+        match tokio::time::timeout(grace_period, stage).await {
+            Ok(result) => result,
+            Err(_) => {
+                tracing::warn!(run_id = %self.run_id(), "Killing a worker that didn't stop");
+                kill();
+                Err(RunFailed::Cancelled {
+                    run_id: self.run_id(),
+                }
+                .into())
+            }
+        }
+    }
+
+    async fn cancelled(&self) {
+        loop {
+            // Make sure we don't miss a notification sent between checking
+            // the flag and waiting.
+            let notified = self.0.notify.notified();
+
+            if self.is_cancelled() {
+                return;
+            }
+
+            notified.await;
+        }
+    }
+}
+
+/// Did this error happen because the run was cancelled?
+pub(crate) fn is_cancelled(error: &Error) -> bool {
+    matches!(error.downcast_ref(), Some(RunFailed::Cancelled { .. }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_are_forgotten_when_they_finish() {
+        let analyses = RunningAnalyses::default();
+        let run_id = Uuid::new_v4();
+
+        let registration = analyses.register(run_id).unwrap();
+        assert!(analyses.register(run_id).is_err());
+        assert!(analyses.cancel(run_id));
+        assert!(registration.token().is_cancelled());
+
+        drop(registration);
+
+        assert!(!analyses.cancel(run_id));
+    }
+
+    #[test]
+    fn wait_for_the_worker_to_stop() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        let token = CancellationToken::new(Uuid::new_v4());
+        let stopped = Arc::new(Notify::new());
+        let mut killed = false;
+
+        let result = runtime.block_on(async {
+            let cancel = {
+                let token = token.clone();
+                async move { token.cancel() }
+            };
+            // The worker finishes its current node before stopping
+            let stage = {
+                let stopped = Arc::clone(&stopped);
+                let run_id = token.run_id();
+                async move {
+                    stopped.notified().await;
+                    Err::<(), _>(RunFailed::Cancelled { run_id }.into())
+                }
+            };
+            let stage = token.stop_gracefully(
+                stage,
+                || stopped.notify_one(),
+                || killed = true,
+                Duration::from_secs(60),
+            );
+
+            future::join(stage, cancel).await.0
+        });
+
+        assert!(is_cancelled(&result.unwrap_err()));
+        assert!(!killed);
+    }
+
+    #[test]
+    fn kill_workers_that_do_not_stop() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        let token = CancellationToken::new(Uuid::new_v4());
+        let mut killed = false;
+
+        let result = runtime.block_on(async {
+            let cancel = {
+                let token = token.clone();
+                async move { token.cancel() }
+            };
+            let stage = token.stop_gracefully(
+                future::pending::<Result<(), Error>>(),
+                || {},
+                || killed = true,
+                Duration::ZERO,
+            );
+
+            future::join(stage, cancel).await.0
+        });
+
+        assert!(is_cancelled(&result.unwrap_err()));
+        assert!(killed);
+    }
+}
@@ -0,0 +1,271 @@
+//! Capturing the intermediate tensors from a run so a misbehaving pipeline
+//! can be inspected node-by-node.
+//!
+//! Each debug run gets its own directory under `$home_dir/debug_runs/`, where
+//! every tensor passed into or out of a node is saved as an Arrow IPC stream
+//! alongside a `run.json` manifest (see [`DebugRun`]).
+
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::{Component, Path, PathBuf},
+};
+
+use anyhow::{Context, Error};
+use arrow::ipc::writer::StreamWriter;
+use hotg_rune_runtime::zune::ZuneEngine;
+use ts_rs::TS;
+use uuid::Uuid;
+
+use crate::{
+    runtime::InputSignatures,
+    shared::{tensors_to_record_batch, ElementType, SerializableError, Tensor},
+    sql::ARROW_CONTENT_TYPE,
+    AppState, BlobStore,
+};
+
+/// The directory (relative to the home directory) debug runs are saved to.
+const DEBUG_RUNS_DIR: &str = "debug_runs";
+
+const MANIFEST: &str = "run.json";
+
+/// Get the list of tensors that were captured during a debug run.
+#[tauri::command]
+#[tracing::instrument(skip(app), err)]
+pub async fn get_debug_run(
+    app: tauri::State<'_, AppState>,
+    run_id: Uuid,
+) -> Result<DebugRun, SerializableError> {
+    let dir = run_dir(app.home_dir(), run_id);
+    let run = tokio::task::spawn_blocking(move || DebugRun::load(&dir)).await??;
+
+    Ok(run)
+}
+
+/// Read one of the tensors captured during a debug run, returning the ID of a
+/// blob containing it as an Arrow IPC stream.
+#[tauri::command]
+#[tracing::instrument(skip(app, blobs), err)]
+pub async fn read_captured_tensor(
+    app: tauri::State<'_, AppState>,
+    blobs: tauri::State<'_, BlobStore>,
+    run_id: Uuid,
+    node: String,
+    direction: TensorDirection,
+    name: String,
+) -> Result<Uuid, SerializableError> {
+    let dir = run_dir(app.home_dir(), run_id);
+
+    let bytes = tokio::task::spawn_blocking(move || {
+        let run = DebugRun::load(&dir)?;
+        let tensor = run
+            .tensors
+            .iter()
+            .find(|t| t.node == node && t.direction == direction && t.name == name)
+            .with_context(|| {
+                format!("The \"{node}\" node has no {direction:?} tensor called \"{name}\"")
+            })?;
+        let path = dir.join(relative_path(&tensor.file)?);
+
+        std::fs::read(&path).with_context(|| format!("Unable to read \"{}\"", path.display()))
+    })
+    .await??;
+
+    Ok(blobs.insert(ARROW_CONTENT_TYPE, bytes))
+}
+
+/// Delete the tensors captured during a debug run.
+#[tauri::command]
+#[tracing::instrument(skip(app), err)]
+pub async fn delete_debug_run(
+    app: tauri::State<'_, AppState>,
+    run_id: Uuid,
+) -> Result<(), SerializableError> {
+    let dir = run_dir(app.home_dir(), run_id);
+    tokio::fs::remove_dir_all(&dir)
+        .await
+        .with_context(|| format!("Unable to delete \"{}\"", dir.display()))?;
+
+    Ok(())
+}
+
+/// Every tensor that was captured during a debug run.
+#[derive(Debug, Clone, PartialEq, Eq, TS, serde::Serialize, serde::Deserialize)]
+#[ts(export, export_to = "../src/backend/types/")]
+pub struct DebugRun {
+    pub run_id: Uuid,
+    pub tensors: Vec<CapturedTensor>,
+}
+
+impl DebugRun {
+    fn load(dir: &Path) -> Result<Self, Error> {
+        let path = dir.join(MANIFEST);
+        let json = std::fs::read_to_string(&path)
+            .with_context(|| format!("Unable to read \"{}\"", path.display()))?;
+
+        serde_json::from_str(&json).context("Unable to parse the debug run's manifest")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, TS, serde::Serialize, serde::Deserialize)]
+#[ts(export, export_to = "../src/backend/types/")]
+pub struct CapturedTensor {
+    pub node: String,
+    pub direction: TensorDirection,
+    pub name: String,
+    pub element_type: ElementType,
+    pub dimensions: Vec<usize>,
+    /// Where the tensor was saved, relative to the run's directory.
+    pub file: String,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, TS, serde::Serialize, serde::Deserialize)]
+#[ts(export, export_to = "../src/backend/types/")]
+#[serde(rename_all = "snake_case")]
+pub enum TensorDirection {
+    Input,
+    Output,
+}
+
+fn run_dir(home_dir: &Path, run_id: Uuid) -> PathBuf {
+    home_dir.join(DEBUG_RUNS_DIR).join(run_id.to_string())
+}
+
+/// Make sure a file mentioned in the manifest can't point outside the run's
+/// directory.
+fn relative_path(file: &str) -> Result<&Path, Error> {
+    let path = Path::new(file);
+
+    if !file.is_empty() && path.components().all(|c| matches!(c, Component::Normal(_))) {
+        Ok(path)
+    } else {
+        anyhow::bail!("\"{file}\" isn't a file inside the debug run's directory")
+    }
+}
+
+/// Where to save the tensors from a debug run.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub(crate) struct Capture {
+    pub run_id: Uuid,
+    dir: PathBuf,
+}
+
+impl Capture {
+    pub(crate) fn new(home_dir: &Path) -> Self {
+        let run_id = Uuid::new_v4();
+
+        Capture {
+            run_id,
+            dir: run_dir(home_dir, run_id),
+        }
+    }
+}
+
+/// Save the tensors passed into and out of each node after a run.
+pub(crate) fn capture(
+    engine: &mut ZuneEngine,
+    signatures: &InputSignatures,
+    Capture { run_id, dir }: &Capture,
+) -> Result<DebugRun, Error> {
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Unable to create the \"{}\" directory", dir.display()))?;
+
+    // Capabilities don't have any inputs, so they won't have signatures
+    let mut nodes: Vec<String> = signatures
+        .nodes()
+        .map(|(node, _)| node.to_string())
+        .chain(engine.input_nodes().iter().cloned())
+        .collect();
+    nodes.sort();
+    nodes.dedup();
+
+    let mut tensors = Vec::new();
+
+    for (n, node) in nodes.iter().enumerate() {
+        let inputs = engine.get_input_tensor_names(node).unwrap_or_default();
+        let outputs = engine.get_output_tensor_names(node).unwrap_or_default();
+        let names = inputs
+            .into_iter()
+            .map(|name| (TensorDirection::Input, name))
+            .chain(
+                outputs
+                    .into_iter()
+                    .map(|name| (TensorDirection::Output, name)),
+            );
+
+        for (i, (direction, tensor_name)) in names.enumerate() {
+            let tensor = match direction {
+                TensorDirection::Input => engine.get_input_tensor(node, &tensor_name),
+                TensorDirection::Output => engine.get_output_tensor(node, &tensor_name),
+            };
+            let tensor = match tensor {
+                Some(t) => Tensor::from(t),
+                None => continue,
+            };
+
+            let file = format!("{n}-{}-{i}.arrow", file_name(node));
+            save(&dir.join(&file), &tensor_name, &tensor)?;
+
+            tensors.push(CapturedTensor {
+                node: node.clone(),
+                direction,
+                name: tensor_name,
+                element_type: tensor.element_type,
+                dimensions: tensor.dimensions,
+                file,
+            });
+        }
+    }
+
+    let run = DebugRun {
+        run_id: *run_id,
+        tensors,
+    };
+    let json = serde_json::to_string_pretty(&run)?;
+    std::fs::write(dir.join(MANIFEST), json).context("Unable to save the debug run's manifest")?;
+
+    Ok(run)
+}
+
+fn save(path: &Path, name: &str, tensor: &Tensor) -> Result<(), Error> {
+    let batch = tensors_to_record_batch([(name, tensor)])?;
+    let f = File::create(path)
+        .with_context(|| format!("Unable to open \"{}\" for writing", path.display()))?;
+
+    let mut writer = StreamWriter::try_new(BufWriter::new(f), &batch.schema())?;
+    writer.write(&batch)?;
+    writer.finish()?;
+
+    Ok(())
+}
+
+/// Make sure a node name is safe to use in a file name.
+fn file_name(node: &str) -> String {
+    node.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_read_files_inside_the_run_directory() {
+        assert_eq!(
+            relative_path("0-argmax-1.arrow").unwrap(),
+            Path::new("0-argmax-1.arrow")
+        );
+        assert!(relative_path("").is_err());
+        assert!(relative_path("../other-run/run.json").is_err());
+        assert!(relative_path("nested/../../secret").is_err());
+        assert!(relative_path("./0-argmax-1.arrow").is_err());
+        assert!(relative_path("/etc/passwd").is_err());
+    }
+}
@@ -0,0 +1,369 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{self, Display, Formatter},
+};
+
+use anyhow::{Context, Error};
+use hotg_rune_runtime::zune::{TensorResult, ZuneEngine};
+use ts_rs::TS;
+
+use crate::{
+    compiler::{parse_element_type, Runefile},
+    runtime::{inspect::read_archive, RunFailed},
+    shared::{DimensionMismatch, Dimensions, ElementType, Tensor},
+};
+
+/// Bind a tensor to one of a node's inputs.
+#[derive(Debug, Clone, PartialEq, Eq, TS, serde::Serialize, serde::Deserialize)]
+#[ts(export, export_to = "../src/backend/types/")]
+pub struct InputBinding {
+    pub node_name: String,
+    pub input_tensor_name: String,
+    pub tensor: Tensor,
+}
+
+/// An [`InputBinding`] that doesn't match what the node expects.
+#[derive(Debug, Clone, PartialEq, Eq, TS, serde::Serialize, serde::Deserialize)]
+#[ts(export, export_to = "../src/backend/types/")]
+pub struct InputMismatch {
+    pub node_name: String,
+    pub input_tensor_name: String,
+    pub reason: MismatchReason,
+}
+
+impl Display for InputMismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let InputMismatch {
+            node_name,
+            input_tensor_name,
+            reason,
+        } = self;
+
+        write!(f, "{node_name}.{input_tensor_name}: ")?;
+
+        match reason {
+            MismatchReason::UnknownNode => write!(f, "there is no node with this name"),
+            MismatchReason::UnknownTensor { available } => write!(
+                f,
+                "the node doesn't have this input (expected one of {})",
+                available.join(", ")
+            ),
+            MismatchReason::DuplicateBinding => write!(f, "the input was bound more than once"),
+            MismatchReason::ElementType { expected, actual } => {
+                write!(f, "expected {expected:?} elements but found {actual:?}")
+            }
+            MismatchReason::Dimensions {
+                expected,
+                actual,
+                reason,
+            } => {
+                write!(
+                    f,
+                    "expected dimensions {expected} but found {actual:?} ({reason})"
+                )
+            }
+            MismatchReason::BufferLength { expected, actual } => {
+                write!(
+                    f,
+                    "expected a {expected} byte buffer but found {actual} bytes"
+                )
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, TS, serde::Serialize, serde::Deserialize)]
+#[ts(export, export_to = "../src/backend/types/")]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum MismatchReason {
+    UnknownNode,
+    UnknownTensor {
+        /// The inputs this node does have.
+        available: Vec<String>,
+    },
+    DuplicateBinding,
+    ElementType {
+        expected: ElementType,
+        actual: ElementType,
+    },
+    /// The tensor's shape doesn't match.
+    Dimensions {
+        expected: Dimensions,
+        actual: Vec<usize>,
+        reason: DimensionMismatch,
+    },
+    /// The buffer's length is inconsistent with the tensor's element type and
+    /// dimensions.
+    BufferLength {
+        expected: usize,
+        actual: usize,
+    },
+}
+
+/// The input tensors accepted by each node in a Rune.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct InputSignatures(HashMap<String, Vec<InputSignature>>);
+
+#[derive(Debug, Clone, PartialEq)]
+struct InputSignature {
+    name: String,
+    element_type: Option<ElementType>,
+    dimensions: Dimensions,
+}
+
+impl InputSignatures {
+    /// Figure out what inputs each node accepts, using the tensor names from
+    /// the runtime and the types declared in the Runefile embedded in the
+    /// archive.
+    pub(crate) fn load(engine: &mut ZuneEngine, rune: &[u8]) -> Result<Self, Error> {
+        let (runefile, _) = read_archive(rune)?;
+        let runefile = runefile
+            .as_deref()
+            .map(Runefile::parse)
+            .transpose()
+            .context("Unable to parse the embedded Runefile")?
+            .unwrap_or_default();
+
+        let mut nodes: Vec<String> = runefile.pipeline.keys().cloned().collect();
+        nodes.extend(engine.input_nodes().iter().cloned());
+        nodes.sort();
+        nodes.dedup();
+
+        let mut signatures = HashMap::new();
+
+        for node in nodes {
+            let tensor_names = match engine.get_input_tensor_names(&node) {
+                Ok(names) => names,
+                Err(_) => continue,
+            };
+            let declared = runefile.declared_inputs(&node).unwrap_or_default();
+
+            let inputs = tensor_names
+                .into_iter()
+                .enumerate()
+                .map(|(i, name)| {
+                    let declared = declared.get(i).copied().flatten();
+                    InputSignature {
+                        name,
+                        element_type: declared.and_then(|t| parse_element_type(&t.element_type)),
+                        dimensions: declared
+                            .filter(|t| !t.dimensions.is_empty())
+                            .map(|t| Dimensions::with_wildcards(&t.dimensions))
+                            .unwrap_or(Dimensions::Dynamic),
+                    }
+                })
+                .collect();
+
+            signatures.insert(node, inputs);
+        }
+
+        Ok(InputSignatures(signatures))
+    }
+
+    /// Every node and the names of its input tensors.
+    pub(crate) fn nodes(&self) -> impl Iterator<Item = (&str, Vec<&str>)> + '_ {
+        self.0.iter().map(|(node, inputs)| {
+            let names = inputs.iter().map(|i| i.name.as_str()).collect();
+            (node.as_str(), names)
+        })
+    }
+
+    /// Check every binding and, if they are all valid, set the Rune's input
+    /// tensors.
+    ///
+    /// Nothing is set if any of the bindings are invalid. Instead, a
+    /// [`RunFailed::InvalidInputs`] is returned with every mismatch.
+    pub(crate) fn bind(
+        &self,
+        engine: &mut ZuneEngine,
+        bindings: Vec<InputBinding>,
+    ) -> Result<(), Error> {
+        let mismatches = self.check(&bindings);
+
+        if !mismatches.is_empty() {
+            return Err(RunFailed::InvalidInputs { mismatches }.into());
+        }
+
+        for InputBinding {
+            node_name,
+            input_tensor_name,
+            tensor,
+        } in bindings
+        {
+            let tensor = TensorResult::from(tensor);
+            tracing::debug!(
+                %node_name,
+                %input_tensor_name,
+                ?tensor.element_type,
+                ?tensor.dimensions,
+                buffer_length = tensor.buffer.len(),
+                "Setting an input tensor",
+            );
+            engine.set_input_tensor(&node_name, &input_tensor_name, &tensor);
+        }
+
+        Ok(())
+    }
+
+    fn check(&self, bindings: &[InputBinding]) -> Vec<InputMismatch> {
+        let mut mismatches = Vec::new();
+        let mut seen = HashSet::new();
+
+        for binding in bindings {
+            let InputBinding {
+                node_name,
+                input_tensor_name,
+                tensor,
+            } = binding;
+
+            let reason = if !seen.insert((node_name, input_tensor_name)) {
+                Some(MismatchReason::DuplicateBinding)
+            } else {
+                match self.0.get(node_name) {
+                    None => Some(MismatchReason::UnknownNode),
+                    Some(inputs) => match inputs.iter().find(|i| i.name == *input_tensor_name) {
+                        None => Some(MismatchReason::UnknownTensor {
+                            available: inputs.iter().map(|i| i.name.clone()).collect(),
+                        }),
+                        Some(signature) => check_tensor(signature, tensor),
+                    },
+                }
+            };
+
+            if let Some(reason) = reason {
+                mismatches.push(InputMismatch {
+                    node_name: node_name.clone(),
+                    input_tensor_name: input_tensor_name.clone(),
+                    reason,
+                });
+            }
+        }
+
+        mismatches
+    }
+}
+
+fn check_tensor(signature: &InputSignature, tensor: &Tensor) -> Option<MismatchReason> {
+    if let Some(expected) = signature.element_type {
+        if expected != tensor.element_type {
+            return Some(MismatchReason::ElementType {
+                expected,
+                actual: tensor.element_type,
+            });
+        }
+    }
+
+    let actual = Dimensions::from(tensor.dimensions.as_slice());
+    if let Err(reason) = signature.dimensions.check(&actual) {
+        return Some(MismatchReason::Dimensions {
+            expected: signature.dimensions.clone(),
+            actual: tensor.dimensions.clone(),
+            reason,
+        });
+    }
+
+    if let Some(size) = tensor.element_type.size_of() {
+        let expected = tensor.dimensions.iter().product::<usize>() * size;
+        if expected != tensor.buffer.len() {
+            return Some(MismatchReason::BufferLength {
+                expected,
+                actual: tensor.buffer.len(),
+            });
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signatures() -> InputSignatures {
+        let mut nodes = HashMap::new();
+        nodes.insert(
+            "input".to_string(),
+            vec![InputSignature {
+                name: "input_0".to_string(),
+                element_type: Some(ElementType::F32),
+                dimensions: Dimensions::with_wildcards(&[0, 4]),
+            }],
+        );
+
+        InputSignatures(nodes)
+    }
+
+    fn binding(node_name: &str, input_tensor_name: &str, tensor: Tensor) -> InputBinding {
+        InputBinding {
+            node_name: node_name.to_string(),
+            input_tensor_name: input_tensor_name.to_string(),
+            tensor,
+        }
+    }
+
+    fn f32_tensor(dimensions: Vec<usize>) -> Tensor {
+        let len: usize = dimensions.iter().product();
+        Tensor {
+            element_type: ElementType::F32,
+            dimensions,
+            buffer: vec![0; len * 4],
+        }
+    }
+
+    #[test]
+    fn valid_bindings_have_no_mismatches() {
+        let bindings = vec![binding("input", "input_0", f32_tensor(vec![3, 4]))];
+
+        assert!(signatures().check(&bindings).is_empty());
+    }
+
+    #[test]
+    fn report_every_mismatch() {
+        let bindings = vec![
+            binding("missing", "input_0", f32_tensor(vec![1, 4])),
+            binding("input", "other", f32_tensor(vec![1, 4])),
+            binding("input", "input_0", f32_tensor(vec![1, 5])),
+        ];
+
+        let reasons: Vec<_> = signatures()
+            .check(&bindings)
+            .into_iter()
+            .map(|m| m.reason)
+            .collect();
+
+        assert_eq!(
+            reasons,
+            vec![
+                MismatchReason::UnknownNode,
+                MismatchReason::UnknownTensor {
+                    available: vec!["input_0".to_string()]
+                },
+                MismatchReason::Dimensions {
+                    expected: Dimensions::Fixed(vec![None, Some(4)]),
+                    actual: vec![1, 5],
+                    reason: DimensionMismatch::Length {
+                        dimension: 1,
+                        expected: 4,
+                        actual: 5
+                    },
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn buffer_must_match_the_dimensions() {
+        let mut tensor = f32_tensor(vec![1, 4]);
+        tensor.buffer.pop();
+
+        let mismatches = signatures().check(&[binding("input", "input_0", tensor)]);
+
+        assert_eq!(
+            mismatches[0].reason,
+            MismatchReason::BufferLength {
+                expected: 16,
+                actual: 15
+            }
+        );
+    }
+}
@@ -0,0 +1,239 @@
+use std::io::{Cursor, Read};
+
+use anyhow::{Context, Error};
+use hotg_rune_runtime::zune::{TensorResult, ZuneEngine};
+use ts_rs::TS;
+use uuid::Uuid;
+use zip::ZipArchive;
+
+use crate::{
+    compiler::Runefile,
+    shared::{ElementType, SerializableError, Tensor},
+    BlobStore,
+};
+
+const RUNEFILE: &str = "Runefile.yml";
+
+/// Load a compiled Rune (referenced by its ID in the [`BlobStore`]) and
+/// describe what is inside it.
+#[tauri::command]
+#[tracing::instrument(skip(blobs), err)]
+pub async fn inspect_rune(
+    blobs: tauri::State<'_, BlobStore>,
+    rune: Uuid,
+) -> Result<RuneDescription, SerializableError> {
+    let rune = blobs.get_bytes(rune)?;
+    let description = tokio::task::spawn_blocking(move || inspect(&rune)).await??;
+
+    Ok(description)
+}
+
+/// Everything we know about a compiled Rune.
+#[derive(Debug, Clone, PartialEq, Eq, TS, serde::Serialize, serde::Deserialize)]
+#[ts(export, export_to = "../src/backend/types/")]
+pub struct RuneDescription {
+    /// The Runefile this Rune was compiled from, if it was embedded in the
+    /// archive.
+    pub runefile: Option<String>,
+    pub nodes: Vec<NodeDescription>,
+    pub resources: Vec<ResourceDescription>,
+    /// Every file inside the archive.
+    pub files: Vec<ArchiveFile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, TS, serde::Serialize, serde::Deserialize)]
+#[ts(export, export_to = "../src/backend/types/")]
+pub struct NodeDescription {
+    pub name: String,
+    pub kind: RuneNodeKind,
+    /// The proc-block or model this node runs, if there is one.
+    pub identifier: Option<String>,
+    /// Is this one of the nodes the caller provides inputs to?
+    pub is_input: bool,
+    /// Is this one of the nodes results are read from?
+    pub is_output: bool,
+    pub inputs: Vec<TensorDescription>,
+    pub outputs: Vec<TensorDescription>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, TS, serde::Serialize, serde::Deserialize)]
+#[ts(export, export_to = "../src/backend/types/")]
+#[serde(rename_all = "snake_case")]
+pub enum RuneNodeKind {
+    Capability,
+    Model,
+    ProcBlock,
+    Output,
+    /// The node doesn't appear in the Runefile.
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, TS, serde::Serialize, serde::Deserialize)]
+#[ts(export, export_to = "../src/backend/types/")]
+pub struct TensorDescription {
+    pub name: String,
+    /// The tensor's element type, if the runtime has allocated it yet.
+    pub element_type: Option<ElementType>,
+    pub dimensions: Vec<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, TS, serde::Serialize, serde::Deserialize)]
+#[ts(export, export_to = "../src/backend/types/")]
+pub struct ResourceDescription {
+    pub name: String,
+    /// Where the resource was loaded from when the Rune was compiled.
+    pub path: Option<String>,
+    /// The resource's size, if it was embedded in the archive.
+    pub bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, TS, serde::Serialize, serde::Deserialize)]
+#[ts(export, export_to = "../src/backend/types/")]
+pub struct ArchiveFile {
+    pub path: String,
+    pub bytes: u64,
+}
+
+pub(crate) fn inspect(rune: &[u8]) -> Result<RuneDescription, Error> {
+    let (runefile, files) = read_archive(rune)?;
+    let parsed = runefile
+        .as_deref()
+        .map(Runefile::parse)
+        .transpose()
+        .context("Unable to parse the embedded Runefile")?
+        .unwrap_or_default();
+
+    let mut engine = ZuneEngine::load(rune).context("Unable to load the Rune")?;
+    let input_nodes = engine.input_nodes().to_vec();
+    let output_nodes = engine.output_nodes().to_vec();
+
+    // Use the Runefile's ordering, but make sure we include anything the
+    // runtime knows about that isn't mentioned in the Runefile
+    let mut names: Vec<String> = parsed.pipeline.keys().cloned().collect();
+    for name in input_nodes.iter().chain(&output_nodes) {
+        if !names.contains(name) {
+            names.push(name.clone());
+        }
+    }
+
+    let mut nodes = Vec::new();
+
+    for name in names {
+        let stage = parsed.pipeline.get(&name);
+        let (kind, identifier) = match stage {
+            Some(s) if s.out.is_some() => (RuneNodeKind::Output, s.out.clone()),
+            Some(s) if s.capability.is_some() => (RuneNodeKind::Capability, s.capability.clone()),
+            Some(s) if s.model.is_some() => (RuneNodeKind::Model, s.model.clone()),
+            Some(s) => (RuneNodeKind::ProcBlock, s.proc_block.clone()),
+            None => (RuneNodeKind::Unknown, None),
+        };
+
+        let inputs = engine
+            .get_input_tensor_names(&name)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|tensor| {
+                let value = engine.get_input_tensor(&name, &tensor);
+                describe_tensor(tensor, value)
+            })
+            .collect();
+        let outputs = engine
+            .get_output_tensor_names(&name)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|tensor| {
+                let value = engine.get_output_tensor(&name, &tensor);
+                describe_tensor(tensor, value)
+            })
+            .collect();
+
+        nodes.push(NodeDescription {
+            is_input: input_nodes.contains(&name),
+            is_output: output_nodes.contains(&name),
+            name,
+            kind,
+            identifier,
+            inputs,
+            outputs,
+        });
+    }
+
+    let resources = parsed
+        .resources
+        .iter()
+        .map(|(name, value)| {
+            let path = value
+                .get("path")
+                .and_then(|p| p.as_str())
+                .map(|p| p.to_string());
+            let bytes = files
+                .iter()
+                .find(|f| f.path.ends_with(&format!("/{name}")) || f.path == *name)
+                .map(|f| f.bytes);
+
+            ResourceDescription {
+                name: name.clone(),
+                path,
+                bytes,
+            }
+        })
+        .collect();
+
+    Ok(RuneDescription {
+        runefile,
+        nodes,
+        resources,
+        files,
+    })
+}
+
+fn describe_tensor(name: String, value: Option<TensorResult>) -> TensorDescription {
+    match value.map(Tensor::from) {
+        Some(Tensor {
+            element_type,
+            dimensions,
+            ..
+        }) => TensorDescription {
+            name,
+            element_type: Some(element_type),
+            dimensions,
+        },
+        None => TensorDescription {
+            name,
+            element_type: None,
+            dimensions: Vec::new(),
+        },
+    }
+}
+
+/// Read the Runefile and get a listing of every file in a Rune archive.
+pub(crate) fn read_archive(rune: &[u8]) -> Result<(Option<String>, Vec<ArchiveFile>), Error> {
+    let mut archive = ZipArchive::new(Cursor::new(rune)).context("Unable to open the archive")?;
+    let mut runefile = None;
+    let mut files = Vec::new();
+
+    for i in 0..archive.len() {
+        let mut entry = archive
+            .by_index(i)
+            .with_context(|| format!("Unable to read entry {i} from the archive"))?;
+
+        if entry.is_dir() {
+            continue;
+        }
+
+        if entry.name() == RUNEFILE {
+            let mut src = String::new();
+            entry
+                .read_to_string(&mut src)
+                .context("Unable to read the Runefile")?;
+            runefile = Some(src);
+        }
+
+        files.push(ArchiveFile {
+            path: entry.name().to_string(),
+            bytes: entry.size(),
+        });
+    }
+
+    Ok((runefile, files))
+}
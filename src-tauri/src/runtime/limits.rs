@@ -0,0 +1,459 @@
+//! Bounding how much time and memory a Rune may use.
+//!
+//! [`ZuneEngine`] creates its own wasmer store internally, so we can't attach
+//! custom tunables or a metering middleware to it. Instead,
+//!
+//! - Linear memory is capped by rewriting the memory section of each
+//!   WebAssembly module in the archive so its maximum size is within the
+//!   limit. A module that needs more than the limit just to start is
+//!   reported as [`RunFailed::ResourceExhausted`]. Once a running module hits
+//!   the limit, `memory.grow` fails and the module will trap (usually with an
+//!   allocation failure), which is reported like any other failed run
+//! - Each Rune runs in its own worker process (see [`Engine`]), and the
+//!   worker is killed once the Rune exceeds a wall-clock timeout
+//!
+//! Instruction metering would need a wasmer middleware, so it isn't
+//! supported. The timeout is what stops a proc-block that is stuck in a loop.
+
+use std::{
+    collections::VecDeque,
+    io::{Cursor, Read, Write},
+    sync::{Arc, Mutex, PoisonError},
+    time::Duration,
+};
+
+use anyhow::{Context, Error};
+use bytes::Bytes;
+use sha2::{Digest, Sha256};
+use tokio::task::JoinHandle;
+use ts_rs::TS;
+use zip::{write::FileOptions, ZipArchive, ZipWriter};
+
+use crate::runtime::{
+    cancellation::CancellationToken,
+    worker::{Engine, Worker},
+    RunFailed,
+};
+
+/// The size of a WebAssembly page.
+const PAGE_SIZE: usize = 64 * 1024;
+
+/// The WebAssembly memory section's ID.
+const MEMORY_SECTION: u8 = 5;
+
+/// Memory limit flags.
+const HAS_MAXIMUM: u8 = 0x01;
+const SHARED: u8 = 0x02;
+const MEMORY_64: u8 = 0x04;
+
+/// The most pages a 32-bit memory can have (i.e. 4 GiB).
+const MAX_PAGES_32: u64 = 65536;
+
+/// How many archives with their memory limited to keep around, so loading
+/// the same Rune again doesn't need to rewrite it.
+const MAX_LIMITED_ARCHIVES: usize = 8;
+
+/// Get the limits applied to every run.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub fn get_run_limits(limits: tauri::State<'_, ConfiguredLimits>) -> RunLimits {
+    limits.get()
+}
+
+/// Change the limits applied to every run.
+///
+/// Runes that are already loaded keep the memory limit they were loaded with.
+#[tauri::command]
+#[tracing::instrument(skip(limits))]
+pub fn set_run_limits(
+    limits: tauri::State<'_, ConfiguredLimits>,
+    run_limits: RunLimits,
+) -> RunLimits {
+    limits.set(run_limits);
+    run_limits
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, TS, serde::Serialize, serde::Deserialize)]
+#[ts(export, export_to = "../src/backend/types/")]
+pub struct RunLimits {
+    /// How long a Rune may take to load or run before it is stopped, in
+    /// milliseconds.
+    pub timeout_ms: Option<u32>,
+    /// The maximum amount of linear memory each WebAssembly module may use,
+    /// in bytes.
+    pub max_memory_bytes: Option<usize>,
+}
+
+impl RunLimits {
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout_ms.map(|ms| Duration::from_millis(ms.into()))
+    }
+}
+
+impl Default for RunLimits {
+    fn default() -> Self {
+        RunLimits {
+            timeout_ms: Some(30_000),
+            max_memory_bytes: Some(512 * 1024 * 1024),
+        }
+    }
+}
+
+/// The [`RunLimits`] the user has configured.
+#[derive(Debug, Default)]
+pub struct ConfiguredLimits {
+    limits: Mutex<RunLimits>,
+    archives: Arc<LimitedArchives>,
+}
+
+impl ConfiguredLimits {
+    pub fn get(&self) -> RunLimits {
+        *self.limits.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn set(&self, limits: RunLimits) {
+        *self.limits.lock().unwrap_or_else(PoisonError::into_inner) = limits;
+    }
+}
+
+/// Start a worker and load a Rune into it with its memory limited, failing
+/// with [`RunFailed::Timeout`] if that takes too long or
+/// [`RunFailed::Cancelled`] if the run is cancelled.
+pub(crate) async fn load_engine(
+    rune: Bytes,
+    limits: &ConfiguredLimits,
+    cancellation: &CancellationToken,
+) -> Result<Engine, Error> {
+    cancellation.check()?;
+
+    let run_limits = limits.get();
+    let archives = Arc::clone(&limits.archives);
+
+    let mut engine = Engine::spawn()?;
+    let worker = engine.worker();
+
+    let task = tokio::task::spawn_blocking(move || {
+        let rune = match run_limits.max_memory_bytes {
+            Some(max_bytes) => archives.get(rune, max_bytes)?,
+            None => rune,
+        };
+        engine.load(&rune)?;
+
+        Ok(engine)
+    });
+
+    cancellation
+        .guard(&worker, wait_for(task, &worker, &run_limits))
+        .await?
+}
+
+/// Wait for a blocking task that is using a worker, killing the worker and
+/// failing with [`RunFailed::Timeout`] if it takes too long.
+pub(crate) async fn wait_for<T>(
+    task: JoinHandle<T>,
+    worker: &Worker,
+    limits: &RunLimits,
+) -> Result<T, Error> {
+    let result = match limits.timeout() {
+        Some(timeout) => match tokio::time::timeout(timeout, task).await {
+            Ok(result) => result,
+            Err(_) => {
+                // The task is blocked on the worker, so this also frees up
+                // its thread.
+                worker.kill();
+
+                return Err(RunFailed::Timeout {
+                    limit_ms: limits.timeout_ms.unwrap_or_default(),
+                }
+                .into());
+            }
+        },
+        None => task.await,
+    };
+
+    result.context("Unable to wait for the Rune to finish running")
+}
+
+/// Rune archives that have already had their memory limited, keyed by the
+/// original archive's hash and the limit.
+#[derive(Debug, Default)]
+struct LimitedArchives(Mutex<VecDeque<([u8; 32], usize, Bytes)>>);
+
+impl LimitedArchives {
+    fn get(&self, rune: Bytes, max_bytes: usize) -> Result<Bytes, Error> {
+        let digest: [u8; 32] = Sha256::digest(&rune).into();
+
+        {
+            let mut archives = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+            let position = archives
+                .iter()
+                .position(|(d, m, _)| *d == digest && *m == max_bytes);
+
+            if let Some(entry) = position.and_then(|ix| archives.remove(ix)) {
+                let limited = entry.2.clone();
+                archives.push_front(entry);
+                return Ok(limited);
+            }
+        }
+
+        let limited = Bytes::from(limit_memory(&rune, max_bytes)?);
+
+        let mut archives = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        archives.push_front((digest, max_bytes, limited.clone()));
+        archives.truncate(MAX_LIMITED_ARCHIVES);
+
+        Ok(limited)
+    }
+}
+
+/// Rewrite every WebAssembly module in a Rune archive so its linear memory
+/// can't grow beyond `max_bytes`.
+fn limit_memory(rune: &[u8], max_bytes: usize) -> Result<Vec<u8>, Error> {
+    let max_pages = (max_bytes / PAGE_SIZE) as u64;
+    let mut archive = ZipArchive::new(Cursor::new(rune)).context("Unable to open the archive")?;
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+
+    for i in 0..archive.len() {
+        let mut entry = archive
+            .by_index(i)
+            .with_context(|| format!("Unable to read entry {i} from the archive"))?;
+        let name = entry.name().to_string();
+        let options = FileOptions::default().compression_method(entry.compression());
+
+        if entry.is_dir() {
+            writer.add_directory(name, options)?;
+            continue;
+        }
+
+        let mut contents = Vec::new();
+        entry
+            .read_to_end(&mut contents)
+            .with_context(|| format!("Unable to read \"{name}\""))?;
+
+        if contents.starts_with(b"\0asm") {
+            let (limited, min_pages) = cap_memory(&contents, max_pages)
+                .with_context(|| format!("Unable to limit the memory used by \"{name}\""))?;
+
+            if min_pages > max_pages {
+                return Err(RunFailed::ResourceExhausted {
+                    module: name,
+                    required_bytes: min_pages as usize * PAGE_SIZE,
+                    limit_bytes: max_bytes,
+                }
+                .into());
+            }
+
+            contents = limited;
+        }
+
+        writer.start_file(name.as_str(), options)?;
+        writer.write_all(&contents)?;
+    }
+
+    let archive = writer.finish().context("Unable to save the archive")?;
+
+    Ok(archive.into_inner())
+}
+
+/// Set the maximum size of each memory defined by a WebAssembly module to at
+/// most `max_pages`, returning the new module and the largest number of pages
+/// any memory starts with.
+///
+/// Imported memories are provided by the host, so they are left alone. Shared
+/// and 64-bit memories are capped the same way as normal ones, except 32-bit
+/// memories can never have more than [`MAX_PAGES_32`] pages.
+fn cap_memory(wasm: &[u8], max_pages: u64) -> Result<(Vec<u8>, u64), Error> {
+    let (header, mut rest) = wasm.split_at(8.min(wasm.len()));
+    if header.len() != 8 {
+        anyhow::bail!("The module is truncated");
+    }
+
+    let mut module = header.to_vec();
+    let mut largest_minimum = 0;
+
+    while let Some((&id, tail)) = rest.split_first() {
+        rest = tail;
+        let size = read_u32(&mut rest)? as usize;
+        if rest.len() < size {
+            anyhow::bail!("Section {id} is truncated");
+        }
+        let (mut contents, tail) = rest.split_at(size);
+        rest = tail;
+
+        if id != MEMORY_SECTION {
+            module.push(id);
+            write_u32(&mut module, size as u32);
+            module.extend_from_slice(contents);
+            continue;
+        }
+
+        let count = read_u32(&mut contents)?;
+        let mut section = Vec::new();
+        write_u32(&mut section, count);
+
+        for _ in 0..count {
+            let (&flags, tail) = contents
+                .split_first()
+                .context("The memory section is truncated")?;
+            contents = tail;
+
+            if flags & !(HAS_MAXIMUM | SHARED | MEMORY_64) != 0 {
+                anyhow::bail!("Unsupported memory flags, 0x{flags:02x}");
+            }
+
+            // 64-bit memories use u64 limits, everything else uses u32
+            let read_limit = |bytes: &mut &[u8]| {
+                if flags & MEMORY_64 != 0 {
+                    read_u64(bytes)
+                } else {
+                    read_u32(bytes).map(u64::from)
+                }
+            };
+            let max_pages = if flags & MEMORY_64 != 0 {
+                max_pages
+            } else {
+                max_pages.min(MAX_PAGES_32)
+            };
+
+            let min = read_limit(&mut contents)?;
+            let max = if flags & HAS_MAXIMUM != 0 {
+                read_limit(&mut contents)?.min(max_pages)
+            } else {
+                max_pages
+            };
+
+            largest_minimum = largest_minimum.max(min);
+            section.push(flags | HAS_MAXIMUM);
+            write_u64(&mut section, min);
+            write_u64(&mut section, max);
+        }
+
+        module.push(MEMORY_SECTION);
+        write_u32(&mut module, section.len() as u32);
+        module.extend(section);
+    }
+
+    Ok((module, largest_minimum))
+}
+
+/// Read an unsigned 32-bit LEB128 integer.
+fn read_u32(bytes: &mut &[u8]) -> Result<u32, Error> {
+    let value = read_u64(bytes)?;
+    u32::try_from(value).context("Integer too large")
+}
+
+/// Read an unsigned 64-bit LEB128 integer.
+fn read_u64(bytes: &mut &[u8]) -> Result<u64, Error> {
+    let mut value = 0_u64;
+
+    for shift in (0..70).step_by(7) {
+        let (&byte, rest) = bytes.split_first().context("Unexpected end of input")?;
+        *bytes = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+
+    anyhow::bail!("Integer too large")
+}
+
+/// Write an unsigned 32-bit LEB128 integer.
+fn write_u32(buffer: &mut Vec<u8>, value: u32) {
+    write_u64(buffer, value.into());
+}
+
+/// Write an unsigned LEB128 integer.
+fn write_u64(buffer: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+
+        if value == 0 {
+            buffer.push(byte);
+            return;
+        }
+
+        buffer.push(byte | 0x80);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A module with a type section and a memory that starts with `min`
+    /// pages.
+    fn module(min: u8) -> Vec<u8> {
+        let mut wasm = b"\0asm\x01\0\0\0".to_vec();
+        wasm.extend([1, 4, 1, 0x60, 0, 0]);
+        wasm.extend([MEMORY_SECTION, 2, 1, 0x00, min]);
+        wasm
+    }
+
+    #[test]
+    fn cap_the_maximum_memory() {
+        let (limited, min_pages) = cap_memory(&module(1), 300).unwrap();
+
+        let mut expected = module(1);
+        expected.truncate(expected.len() - 5);
+        // flags = 0x01 (has maximum), min = 1, max = 300 as LEB128
+        expected.extend([MEMORY_SECTION, 5, 1, 0x01, 1, 0xac, 0x02]);
+        assert_eq!(limited, expected);
+        assert_eq!(min_pages, 1);
+    }
+
+    #[test]
+    fn cap_shared_and_64_bit_memories() {
+        let mut wasm = b"\0asm\x01\0\0\0".to_vec();
+        // a shared memory with min = 1 and max = 1000, then a 64-bit memory
+        // with min = 2 and no maximum
+        wasm.extend([MEMORY_SECTION, 7, 2, 0x03, 1, 0xe8, 0x07, 0x04, 2]);
+
+        let (limited, min_pages) = cap_memory(&wasm, 300).unwrap();
+
+        let mut expected = b"\0asm\x01\0\0\0".to_vec();
+        expected.extend([
+            MEMORY_SECTION,
+            9,
+            2,
+            0x03,
+            1,
+            0xac,
+            0x02,
+            0x05,
+            2,
+            0xac,
+            0x02,
+        ]);
+        assert_eq!(limited, expected);
+        assert_eq!(min_pages, 2);
+    }
+
+    #[test]
+    fn never_exceed_4_gib_for_32_bit_memories() {
+        let (limited, _) = cap_memory(&module(1), 100_000).unwrap();
+
+        let mut expected = module(1);
+        expected.truncate(expected.len() - 5);
+        // flags = 0x01 (has maximum), min = 1, max = 65536 as LEB128
+        expected.extend([MEMORY_SECTION, 6, 1, 0x01, 1, 0x80, 0x80, 0x04]);
+        assert_eq!(limited, expected);
+    }
+
+    #[test]
+    fn reject_unknown_memory_flags() {
+        let mut wasm = b"\0asm\x01\0\0\0".to_vec();
+        wasm.extend([MEMORY_SECTION, 3, 1, 0x08, 1]);
+
+        assert!(cap_memory(&wasm, 300).is_err());
+    }
+
+    #[test]
+    fn report_the_initial_memory_size() {
+        let (_, min_pages) = cap_memory(&module(3), 2).unwrap();
+
+        assert_eq!(min_pages, 3);
+    }
+}
@@ -1,16 +1,188 @@
+mod batch;
+mod cancellation;
+mod debug;
+mod inputs;
+mod inspect;
+mod limits;
+mod output_table;
+mod profiling;
+mod sessions;
+mod worker;
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    time::Instant,
+};
+
+use anyhow::{Context, Error};
+use hotg_rune_runtime::zune::ZuneEngine;
 use tauri::AppHandle;
+use ts_rs::TS;
 use uuid::Uuid;
 
-use crate::shared::{Analysis, ColumnMapping, Pipeline, SerializableError};
+use crate::shared::{Analysis, ColumnMapping, Pipeline, RuneOutputs, SerializableError, Tensor};
+
+pub use self::inspect::{
+    inspect_rune, ArchiveFile, NodeDescription, ResourceDescription, RuneDescription, RuneNodeKind,
+    TensorDescription,
+};
+pub use self::{
+    batch::{run_over_dataset, DatasetRunProgress, DatasetRunSummary, RunState},
+    cancellation::{cancel_analysis, RunningAnalyses},
+    debug::{
+        delete_debug_run, get_debug_run, read_captured_tensor, CapturedTensor, DebugRun,
+        TensorDirection,
+    },
+    inputs::{InputBinding, InputMismatch, MismatchReason},
+    limits::{get_run_limits, set_run_limits, ConfiguredLimits, RunLimits},
+    sessions::{load_rune, run_rune, unload_rune, Sessions},
+    worker::{run_worker, WORKER_FLAG},
+};
 
+pub(crate) use self::{
+    cancellation::is_cancelled,
+    debug::Capture,
+    inputs::InputSignatures,
+    limits::{load_engine, wait_for},
+    sessions::evict_idle_sessions,
+    worker::Engine,
+};
+
+/// Run a [`Pipeline`] directly.
+///
+/// This isn't implemented yet and always fails with
+/// [`RunFailed::NotImplemented`]. A [`Pipeline`] needs to be compiled into a
+/// Rune before it can be run, so use [`run_over_dataset`] or [`run_rune`]
+/// instead.
 #[tauri::command]
+#[tracing::instrument(skip_all, err)]
 pub fn execute_analysis(
     _app: AppHandle,
     _pipeline: Pipeline,
     _column_mapping: Vec<ColumnMapping>,
-) -> Result<Analysis, SerializableError> {
-    let id = Uuid::new_v4();
-    let _span = tracing::info_span!("execute_analysis", id = %id);
+) -> Result<Analysis, SerializableError<RunFailed>> {
+    Err(Error::from(RunFailed::NotImplemented).into())
+}
+
+/// Why running a Rune failed.
+#[derive(Debug, Clone, PartialEq, Eq, TS, serde::Serialize, serde::Deserialize)]
+#[ts(export, export_to = "../src/backend/types/")]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum RunFailed {
+    /// The session doesn't exist, either because it was unloaded or it sat
+    /// idle for too long.
+    SessionNotFound { session_id: Uuid },
+    /// Some of the input tensors don't match what the Rune expects.
+    InvalidInputs { mismatches: Vec<InputMismatch> },
+    /// The Rune took longer than the configured timeout (see [`RunLimits`]).
+    Timeout { limit_ms: u32 },
+    /// A WebAssembly module needs more memory than it is allowed to use (see
+    /// [`RunLimits`]).
+    ResourceExhausted {
+        module: String,
+        required_bytes: usize,
+        limit_bytes: usize,
+    },
+    /// The run was stopped using [`cancel_analysis`].
+    Cancelled { run_id: Uuid },
+    /// The command isn't supported yet.
+    NotImplemented,
+}
+
+impl Display for RunFailed {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RunFailed::SessionNotFound { session_id } => {
+                write!(
+                    f,
+                    "The \"{session_id}\" session doesn't exist or has expired"
+                )
+            }
+            RunFailed::InvalidInputs { mismatches } => {
+                write!(f, "The inputs don't match what the Rune expects")?;
+
+                for mismatch in mismatches {
+                    write!(f, "\n- {mismatch}")?;
+                }
+
+                Ok(())
+            }
+            RunFailed::Timeout { limit_ms } => {
+                write!(f, "The Rune didn't finish within {limit_ms}ms")
+            }
+            RunFailed::ResourceExhausted {
+                module,
+                required_bytes,
+                limit_bytes,
+            } => write!(
+                f,
+                "\"{module}\" needs {required_bytes} bytes of memory, but modules are limited to {limit_bytes} bytes"
+            ),
+            RunFailed::Cancelled { run_id } => write!(f, "The \"{run_id}\" run was cancelled"),
+            RunFailed::NotImplemented => write!(f, "This isn't implemented yet"),
+        }
+    }
+}
+
+impl std::error::Error for RunFailed {}
+
+/// Set the Rune's inputs, run it, and read back the tensors passed to its
+/// output nodes along with a profile of the run.
+///
+/// If a [`Capture`] is provided, every intermediate tensor will be saved so it
+/// can be inspected later.
+///
+/// `check_cancelled` is called once the inputs have been set. The pipeline
+/// runs in a single call, so it can't be stopped part-way through.
+pub(crate) fn predict(
+    engine: &mut ZuneEngine,
+    signatures: &InputSignatures,
+    inputs: Vec<InputBinding>,
+    capture: Option<&Capture>,
+    check_cancelled: impl FnOnce() -> Result<(), RunFailed>,
+) -> Result<RuneOutputs, Error> {
+    let started = Instant::now();
+    signatures.bind(engine, inputs)?;
+    check_cancelled()?;
+
+    engine.predict().context("Unable to run the Rune")?;
+
+    let mut outputs = read_outputs(engine)?;
+    outputs.profile = profiling::profile(engine, signatures, started.elapsed());
+
+    if let Some(capture) = capture {
+        debug::capture(engine, signatures, capture)
+            .context("Unable to save the intermediate tensors")?;
+        outputs.debug_run = Some(capture.run_id);
+    }
+
+    Ok(outputs)
+}
+
+/// Read the tensors passed to each of the Rune's output nodes.
+fn read_outputs(engine: &mut ZuneEngine) -> Result<RuneOutputs, Error> {
+    let mut outputs = RuneOutputs::default();
+
+    for node in engine.output_nodes().to_vec() {
+        let tensor_names = engine
+            .get_input_tensor_names(&node)
+            .with_context(|| format!("Unable to get the tensors for the \"{node}\" output"))?;
+        let mut tensors = HashMap::new();
+
+        for tensor_name in tensor_names {
+            let tensor = engine
+                .get_input_tensor(&node, &tensor_name)
+                .with_context(|| {
+                    format!(
+                        "Unable to fetch the \"{tensor_name}\" tensor from the \"{node}\" output"
+                    )
+                })?;
+            tensors.insert(tensor_name, Tensor::from(tensor));
+        }
+
+        outputs.nodes.insert(node, tensors);
+    }
 
-    todo!();
+    Ok(outputs)
 }
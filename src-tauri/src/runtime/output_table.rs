@@ -0,0 +1,339 @@
+//! Saving the tensors produced by a Rune to a DuckDB table so they can be
+//! joined back onto the input data.
+
+use std::{collections::BTreeSet, sync::Arc};
+
+use anyhow::{Context, Error};
+use arrow::{
+    array::{ArrayRef, Int64Array},
+    datatypes::{DataType, Field, Schema},
+    record_batch::RecordBatch,
+};
+use duckdb::{params, Connection};
+
+use crate::{shared::RuneOutputs, AppState};
+
+/// The column containing the index of the input row each output came from.
+const ROW_ID: &str = "row_id";
+
+/// A table that outputs are appended to, one batch at a time.
+///
+/// Each output tensor gets its own column (named `node.tensor`) as long as
+/// its first dimension matches the number of rows in the batch, so outputs
+/// line up with the rows they were calculated from. Tensors with more than
+/// one dimension are stored as lists.
+///
+/// The columns are decided by the first batch. Outputs that were skipped then
+/// are skipped for every batch, and it is an error for a saved output to stop
+/// lining up with the rows later on.
+#[derive(Debug)]
+pub(crate) struct OutputTable {
+    name: String,
+    /// The `node.tensor` names of the outputs being saved, in order, once the
+    /// first batch has been seen.
+    columns: Option<Vec<(String, String)>>,
+    /// Outputs that couldn't be lined up with the input rows.
+    skipped: BTreeSet<String>,
+}
+
+impl OutputTable {
+    pub fn new(name: String) -> Self {
+        OutputTable {
+            name,
+            columns: None,
+            skipped: BTreeSet::new(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn skipped(&self) -> Vec<String> {
+        self.skipped.iter().cloned().collect()
+    }
+
+    /// Save the outputs from a batch of `rows` rows, starting at `first_row`.
+    pub async fn append(
+        &mut self,
+        app: &AppState,
+        first_row: usize,
+        rows: usize,
+        outputs: &RuneOutputs,
+    ) -> Result<(), Error> {
+        let is_first_batch = self.columns.is_none();
+        let batch = self.record_batch(first_row, rows, outputs)?;
+
+        let db = app.db().await;
+        if is_first_batch {
+            prepare_table(&db, &self.name, &batch.schema())?;
+        }
+
+        write(&db, &self.name, &batch)
+    }
+
+    fn record_batch(
+        &mut self,
+        first_row: usize,
+        rows: usize,
+        outputs: &RuneOutputs,
+    ) -> Result<RecordBatch, Error> {
+        let columns = match &self.columns {
+            Some(columns) => columns.clone(),
+            None => {
+                let columns = self.choose_columns(rows, outputs);
+                self.columns = Some(columns.clone());
+                columns
+            }
+        };
+
+        let row_ids = (first_row..first_row + rows).map(|i| i as i64);
+        let mut fields = vec![Field::new(ROW_ID, DataType::Int64, false)];
+        let mut arrays: Vec<ArrayRef> = vec![Arc::new(Int64Array::from_iter_values(row_ids))];
+
+        for (node, tensor_name) in &columns {
+            let name = format!("{node}.{tensor_name}");
+            let tensor = outputs
+                .nodes
+                .get(node)
+                .and_then(|tensors| tensors.get(tensor_name))
+                .with_context(|| format!("The Rune didn't produce \"{name}\" this time"))?;
+
+            anyhow::ensure!(
+                tensor.dimensions.first() == Some(&rows),
+                "\"{name}\" no longer lines up with the input rows (dimensions: {:?}, rows: {rows})",
+                tensor.dimensions,
+            );
+
+            let array = tensor
+                .to_array()
+                .with_context(|| format!("Unable to convert \"{name}\" to an Arrow array"))?;
+            fields.push(Field::new(&name, array.data_type().clone(), false));
+            arrays.push(array);
+        }
+
+        RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays)
+            .context("Unable to create a record batch from the outputs")
+    }
+
+    /// Work out which outputs from the first batch line up with the input
+    /// rows, remembering the ones that don't.
+    fn choose_columns(&mut self, rows: usize, outputs: &RuneOutputs) -> Vec<(String, String)> {
+        let mut columns = Vec::new();
+
+        for (node, tensors) in &outputs.nodes {
+            for (tensor_name, tensor) in tensors {
+                if tensor.dimensions.first() == Some(&rows) {
+                    columns.push((node.clone(), tensor_name.clone()));
+                    continue;
+                }
+
+                let name = format!("{node}.{tensor_name}");
+                tracing::warn!(
+                    output = %name,
+                    dimensions = ?tensor.dimensions,
+                    rows,
+                    "The output doesn't line up with the input rows, so it won't be saved",
+                );
+                self.skipped.insert(name);
+            }
+        }
+
+        columns.sort();
+        columns
+    }
+}
+
+/// Create the table if it doesn't exist yet, otherwise make sure it has the
+/// columns we are about to save.
+fn prepare_table(conn: &Connection, table: &str, schema: &Schema) -> Result<(), Error> {
+    let expected = schema
+        .fields()
+        .iter()
+        .map(|f| Ok((f.name().clone(), sql_type(f.data_type())?)))
+        .collect::<Result<Vec<_>, Error>>()?;
+    let existing = existing_columns(conn, table)?;
+
+    if existing.is_empty() {
+        conn.execute(&create_table_sql(table, &expected), params![])
+            .with_context(|| format!("Unable to create the \"{table}\" table"))?;
+    } else if existing != expected {
+        anyhow::bail!(
+            "The \"{table}\" table already exists with different columns ({}), so the outputs ({}) can't be saved to it",
+            describe_columns(&existing),
+            describe_columns(&expected),
+        );
+    }
+
+    Ok(())
+}
+
+/// Get the name and type of each column in a table, in order. This will be
+/// empty if the table doesn't exist.
+fn existing_columns(conn: &Connection, table: &str) -> Result<Vec<(String, String)>, Error> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT column_name, data_type FROM information_schema.columns WHERE table_name = ? ORDER BY ordinal_position",
+        )
+        .context("Unable to query the table's schema")?;
+
+    let rows = stmt
+        .query_map(params![table], |row| Ok((row.get(0)?, row.get(1)?)))
+        .context("Unable to query the table's schema")?;
+
+    rows.collect::<Result<_, _>>()
+        .context("Unable to read the table's schema")
+}
+
+fn describe_columns(columns: &[(String, String)]) -> String {
+    columns
+        .iter()
+        .map(|(name, ty)| format!("{name} {ty}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn create_table_sql(table: &str, columns: &[(String, String)]) -> String {
+    let columns: Vec<_> = columns
+        .iter()
+        .map(|(name, ty)| format!("{} {ty}", quote(name)))
+        .collect();
+
+    format!("CREATE TABLE {} ({})", quote(table), columns.join(", "))
+}
+
+fn write(conn: &Connection, table: &str, batch: &RecordBatch) -> Result<(), Error> {
+    let mut appender = conn
+        .appender(table)
+        .with_context(|| format!("Unable to open \"{table}\" for writing"))?;
+
+    appender
+        .append_record_batch(batch.clone())
+        .with_context(|| format!("Unable to save the outputs to \"{table}\""))?;
+    appender
+        .flush()
+        .with_context(|| format!("Unable to save the outputs to \"{table}\""))?;
+
+    Ok(())
+}
+
+fn sql_type(data_type: &DataType) -> Result<String, Error> {
+    let ty = match data_type {
+        DataType::UInt8 => "UTINYINT",
+        DataType::Int8 => "TINYINT",
+        DataType::UInt16 => "USMALLINT",
+        DataType::Int16 => "SMALLINT",
+        DataType::UInt32 => "UINTEGER",
+        DataType::Int32 => "INTEGER",
+        DataType::Float32 => "FLOAT",
+        DataType::UInt64 => "UBIGINT",
+        DataType::Int64 => "BIGINT",
+        DataType::Float64 => "DOUBLE",
+        DataType::Utf8 => "VARCHAR",
+        DataType::FixedSizeList(item, _) => {
+            return Ok(format!("{}[]", sql_type(item.data_type())?))
+        }
+        other => anyhow::bail!("Unable to save a {other} column"),
+    };
+
+    Ok(ty.to_string())
+}
+
+fn quote(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::shared::{ElementType, Tensor};
+
+    fn outputs(tensors: Vec<(&str, Tensor)>) -> RuneOutputs {
+        let mut node = HashMap::new();
+        for (name, tensor) in tensors {
+            node.insert(name.to_string(), tensor);
+        }
+
+        let mut outputs = RuneOutputs::default();
+        outputs.nodes.insert("serial".to_string(), node);
+        outputs
+    }
+
+    fn f32_tensor(dimensions: Vec<usize>, values: &[f32]) -> Tensor {
+        Tensor {
+            element_type: ElementType::F32,
+            dimensions,
+            buffer: values.iter().flat_map(|v| v.to_le_bytes()).collect(),
+        }
+    }
+
+    #[test]
+    fn save_row_aligned_outputs() {
+        let conn = Connection::open_in_memory().unwrap();
+        let mut table = OutputTable::new("predictions".to_string());
+        let outputs = outputs(vec![
+            ("label", f32_tensor(vec![2], &[1.0, f32::NAN])),
+            ("scores", f32_tensor(vec![2, 2], &[0.5, 0.5, 0.25, 0.75])),
+            ("metric", f32_tensor(vec![1], &[0.9])),
+        ]);
+
+        let batch = table.record_batch(10, 2, &outputs).unwrap();
+        prepare_table(&conn, table.name(), &batch.schema()).unwrap();
+        write(&conn, table.name(), &batch).unwrap();
+
+        assert_eq!(
+            existing_columns(&conn, "predictions").unwrap(),
+            vec![
+                ("row_id".to_string(), "BIGINT".to_string()),
+                ("serial.label".to_string(), "FLOAT".to_string()),
+                ("serial.scores".to_string(), "FLOAT[]".to_string()),
+            ]
+        );
+        let rows: i64 = conn
+            .query_row("SELECT COUNT(*) FROM predictions", params![], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(rows, 2);
+        assert_eq!(table.skipped(), vec!["serial.metric".to_string()]);
+    }
+
+    #[test]
+    fn columns_are_decided_by_the_first_batch() {
+        let mut table = OutputTable::new("predictions".to_string());
+        let first = outputs(vec![
+            ("label", f32_tensor(vec![2], &[1.0, 2.0])),
+            ("metric", f32_tensor(vec![1], &[0.9])),
+        ]);
+        // The last batch only has one row, so "metric" happens to line up
+        let last = outputs(vec![
+            ("label", f32_tensor(vec![1], &[3.0])),
+            ("metric", f32_tensor(vec![1], &[0.8])),
+        ]);
+
+        let first = table.record_batch(0, 2, &first).unwrap();
+        let last = table.record_batch(2, 1, &last).unwrap();
+
+        assert_eq!(first.schema(), last.schema());
+        assert_eq!(last.num_columns(), 2);
+        assert_eq!(table.skipped(), vec!["serial.metric".to_string()]);
+    }
+
+    #[test]
+    fn existing_tables_must_have_the_same_columns() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            r#"CREATE TABLE "predictions" ("row_id" BIGINT, "serial.label" VARCHAR)"#,
+            params![],
+        )
+        .unwrap();
+        let mut table = OutputTable::new("predictions".to_string());
+        let outputs = outputs(vec![("label", f32_tensor(vec![1], &[1.0]))]);
+
+        let batch = table.record_batch(0, 1, &outputs).unwrap();
+
+        assert!(prepare_table(&conn, table.name(), &batch.schema()).is_err());
+    }
+}
@@ -0,0 +1,43 @@
+//! Figuring out where the data goes when a Rune is run.
+//!
+//! The pinned runtime only lets us run the entire pipeline at once using
+//! [`ZuneEngine::predict()`], so the run is timed as a whole. The number of
+//! bytes passed into each node is read back from the engine afterwards.
+//!
+//! The runtime doesn't expose per-node timings or how much memory each
+//! WebAssembly instance uses, so those aren't part of the profile.
+
+use std::{collections::HashMap, time::Duration};
+
+use hotg_rune_runtime::zune::ZuneEngine;
+
+use crate::{
+    runtime::InputSignatures,
+    shared::{NodeProfile, RunProfile},
+};
+
+/// Build a [`RunProfile`] for a run that took `elapsed`, using the tensors
+/// each node received.
+pub(crate) fn profile(
+    engine: &mut ZuneEngine,
+    signatures: &InputSignatures,
+    elapsed: Duration,
+) -> RunProfile {
+    let mut nodes = HashMap::new();
+
+    for (node, tensor_names) in signatures.nodes() {
+        // Tensors that were never set don't count towards the total
+        let input_bytes = tensor_names
+            .into_iter()
+            .filter_map(|tensor_name| engine.get_input_tensor(node, tensor_name))
+            .map(|tensor| tensor.buffer.len())
+            .sum();
+
+        nodes.insert(node.to_string(), NodeProfile { input_bytes });
+    }
+
+    RunProfile {
+        elapsed_us: elapsed.as_micros() as u64,
+        nodes,
+    }
+}
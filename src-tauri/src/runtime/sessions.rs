@@ -0,0 +1,205 @@
+//! Long-lived Zune engines, so a Rune doesn't need to be re-instantiated
+//! every time the user wants to try different inputs.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::{Duration, Instant},
+};
+
+use anyhow::Error;
+use tauri::{AppHandle, Manager};
+use uuid::Uuid;
+
+use crate::{
+    runtime::{
+        load_engine, wait_for, worker::Worker, Capture, ConfiguredLimits, Engine, InputBinding,
+        RunFailed, RunningAnalyses,
+    },
+    shared::{RuneOutputs, SerializableError},
+    AppState, BlobStore,
+};
+
+/// The maximum number of sessions that can be alive at a time. Loading a Rune
+/// when we are at the limit will evict the least recently used session.
+const MAX_SESSIONS: usize = 8;
+
+/// Sessions that haven't been used for this long will be unloaded.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// How often to check for idle sessions.
+const EVICTION_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Load a Rune (referenced by its ID in the [`BlobStore`]) and keep it around
+/// so it can be run multiple times, returning the new session's ID.
+///
+/// The session's memory is limited using the [`RunLimits`](crate::runtime::RunLimits) at the time it was
+/// loaded.
+///
+/// Loading can be stopped using [`crate::runtime::cancel_analysis`] with the
+/// `run_id`.
+#[tauri::command]
+#[tracing::instrument(skip(sessions, blobs, limits, analyses), err)]
+pub async fn load_rune(
+    sessions: tauri::State<'_, Sessions>,
+    blobs: tauri::State<'_, BlobStore>,
+    limits: tauri::State<'_, ConfiguredLimits>,
+    analyses: tauri::State<'_, RunningAnalyses>,
+    rune: Uuid,
+    run_id: Option<Uuid>,
+) -> Result<Uuid, SerializableError<RunFailed>> {
+    let registration = analyses.register(run_id.unwrap_or_else(Uuid::new_v4))?;
+    let rune = blobs.get_bytes(rune)?;
+    let engine = load_engine(rune, &limits, registration.token()).await?;
+
+    let session_id = sessions.insert(engine);
+    tracing::debug!(%session_id, "Loaded a Rune");
+
+    Ok(session_id)
+}
+
+/// Run a Rune that was loaded with [`load_rune`].
+///
+/// The inputs are checked against what each node expects before the Rune is
+/// run, failing with [`RunFailed::InvalidInputs`] if anything doesn't match.
+///
+/// The run can be stopped using [`crate::runtime::cancel_analysis`] with the
+/// `run_id`, failing with [`RunFailed::Cancelled`]. The Rune's pipeline can't
+/// be interrupted, so a run that is already in progress gets its worker
+/// killed if it doesn't finish within a few seconds.
+///
+/// If the run times out or its worker has to be killed, the session is
+/// unloaded.
+///
+/// When `debug` is set, every intermediate tensor is saved and can be
+/// inspected using [`crate::runtime::get_debug_run`].
+#[tauri::command]
+#[tracing::instrument(skip(app, sessions, limits, analyses, inputs), err)]
+pub async fn run_rune(
+    app: tauri::State<'_, AppState>,
+    sessions: tauri::State<'_, Sessions>,
+    limits: tauri::State<'_, ConfiguredLimits>,
+    analyses: tauri::State<'_, RunningAnalyses>,
+    session_id: Uuid,
+    inputs: Vec<InputBinding>,
+    debug: Option<bool>,
+    run_id: Option<Uuid>,
+) -> Result<RuneOutputs, SerializableError<RunFailed>> {
+    let registration = analyses.register(run_id.unwrap_or_else(Uuid::new_v4))?;
+    let cancellation = registration.token();
+    let (engine, worker) = sessions
+        .get(session_id)
+        .ok_or_else(|| Error::from(RunFailed::SessionNotFound { session_id }))?;
+    let capture = debug.unwrap_or(false).then(|| Capture::new(app.home_dir()));
+
+    let task = tokio::task::spawn_blocking(move || {
+        let mut engine = engine.lock().unwrap_or_else(PoisonError::into_inner);
+        engine.predict(inputs, capture.as_ref())
+    });
+
+    let result = cancellation
+        .guard(&worker, wait_for(task, &worker, &limits.get()))
+        .await
+        .and_then(|outputs| outputs);
+
+    if result.is_err() && !worker.is_running() {
+        tracing::warn!(%session_id, "Unloading a session whose worker has stopped");
+        sessions.remove(session_id);
+    }
+
+    Ok(result?)
+}
+
+/// Unload a Rune, returning `true` if the session was still alive.
+#[tauri::command]
+#[tracing::instrument(skip(sessions))]
+pub fn unload_rune(sessions: tauri::State<'_, Sessions>, session_id: Uuid) -> bool {
+    sessions.remove(session_id)
+}
+
+/// Every loaded Rune, keyed by session ID.
+#[derive(Default)]
+pub struct Sessions(Mutex<HashMap<Uuid, Session>>);
+
+impl Sessions {
+    fn insert(&self, engine: Engine) -> Uuid {
+        let mut sessions = self.lock();
+
+        while sessions.len() >= MAX_SESSIONS {
+            let oldest = sessions
+                .iter()
+                .min_by_key(|(_, s)| s.last_used)
+                .map(|(id, _)| *id);
+
+            match oldest {
+                Some(session_id) => {
+                    tracing::debug!(%session_id, "Evicting the least recently used session");
+                    sessions.remove(&session_id);
+                }
+                None => break,
+            }
+        }
+
+        let session_id = Uuid::new_v4();
+        sessions.insert(
+            session_id,
+            Session {
+                worker: engine.worker(),
+                engine: Arc::new(Mutex::new(engine)),
+                last_used: Instant::now(),
+            },
+        );
+
+        session_id
+    }
+
+    fn get(&self, session_id: Uuid) -> Option<(Arc<Mutex<Engine>>, Worker)> {
+        let mut sessions = self.lock();
+        let session = sessions.get_mut(&session_id)?;
+        session.last_used = Instant::now();
+
+        Some((Arc::clone(&session.engine), session.worker.clone()))
+    }
+
+    fn remove(&self, session_id: Uuid) -> bool {
+        self.lock().remove(&session_id).is_some()
+    }
+
+    /// Unload any sessions which have been idle for longer than
+    /// [`IDLE_TIMEOUT`].
+    fn evict_idle(&self) {
+        let now = Instant::now();
+
+        self.lock().retain(|session_id, session| {
+            let keep = now.duration_since(session.last_used) < IDLE_TIMEOUT;
+            if !keep {
+                tracing::debug!(%session_id, "Evicting an idle session");
+            }
+            keep
+        });
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<Uuid, Session>> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+struct Session {
+    /// The engine is shared so a session can keep running even if it gets
+    /// evicted part-way through.
+    engine: Arc<Mutex<Engine>>,
+    /// Kept separately so the worker can be killed while the engine is
+    /// locked.
+    worker: Worker,
+    last_used: Instant,
+}
+
+/// Periodically unload sessions that haven't been used in a while.
+pub(crate) async fn evict_idle_sessions(handle: AppHandle) {
+    let mut interval = tokio::time::interval(EVICTION_INTERVAL);
+
+    loop {
+        interval.tick().await;
+        handle.state::<Sessions>().evict_idle();
+    }
+}
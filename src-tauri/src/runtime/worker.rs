@@ -0,0 +1,563 @@
+//! Running Runes in a separate process.
+//!
+//! [`ZuneEngine`] creates its own wasmer store, so there is no way to meter a
+//! Rune or interrupt it from the inside. Instead, each [`Engine`] is a worker
+//! process (the `weld` executable started with [`WORKER_FLAG`]) which owns
+//! the actual [`ZuneEngine`], and a Rune that takes too long is stopped by
+//! killing its worker.
+//!
+//! Requests and responses are sent over the worker's stdin and stdout as
+//! length-prefixed JSON frames. The Rune and any tensor buffers would be
+//! serialized as arrays of numbers, so they are taken out of the message and
+//! sent as raw frames after it instead (see [`Buffers`]). The worker logs to
+//! stderr so nothing else gets mixed in with its responses.
+//!
+//! Requests are read on a separate thread so a run can be cancelled while
+//! the worker is busy. The worker checks for cancellation before and after
+//! running the pipeline, but can't stop it part-way through.
+
+use std::{
+    io::{BufReader, BufWriter, ErrorKind, Read, Write},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+    sync::{mpsc, Arc, Mutex, PoisonError},
+};
+
+use anyhow::{Context, Error};
+use hotg_rune_runtime::zune::ZuneEngine;
+use serde::{de::DeserializeOwned, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    runtime::{predict, Capture, InputBinding, InputSignatures, RunFailed},
+    shared::RuneOutputs,
+};
+
+/// The command-line flag that turns the `weld` executable into a worker.
+pub const WORKER_FLAG: &str = "--rune-worker";
+
+/// A Rune that has been loaded into a worker process.
+///
+/// The worker is killed when the [`Engine`] is dropped.
+#[derive(Debug)]
+pub(crate) struct Engine {
+    worker: Worker,
+    responses: BufReader<ChildStdout>,
+}
+
+impl Engine {
+    /// Start a new worker process, without loading anything into it.
+    pub fn spawn() -> Result<Self, Error> {
+        let exe = std::env::current_exe().context("Unable to find the current executable")?;
+
+        let mut command = Command::new(exe);
+        command
+            .arg(WORKER_FLAG)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit());
+
+        #[cfg(windows)]
+        {
+            use std::os::windows::process::CommandExt;
+
+            // Don't pop up a console window for each worker
+            const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+            command.creation_flags(CREATE_NO_WINDOW);
+        }
+
+        let mut child = command
+            .spawn()
+            .context("Unable to start a worker process")?;
+        let requests = child
+            .stdin
+            .take()
+            .context("Unable to get the worker's stdin")?;
+        let responses = child
+            .stdout
+            .take()
+            .context("Unable to get the worker's stdout")?;
+
+        Ok(Engine {
+            worker: Worker {
+                process: Arc::new(Mutex::new(child)),
+                requests: Arc::new(Mutex::new(BufWriter::new(requests))),
+            },
+            responses: BufReader::new(responses),
+        })
+    }
+
+    /// Get a handle which can stop the worker while it is busy.
+    pub fn worker(&self) -> Worker {
+        self.worker.clone()
+    }
+
+    /// Load a Rune into the worker, blocking until it has been instantiated.
+    pub fn load(&mut self, rune: &[u8]) -> Result<(), Error> {
+        self.worker.send(Request::Load {
+            rune: rune.to_vec(),
+        })?;
+
+        match self.receive()? {
+            Response::Loaded => Ok(()),
+            Response::Failed(e) => Err(e.into()),
+            Response::Predicted(_) => Err(Error::msg("The worker sent an unexpected response")),
+        }
+    }
+
+    /// Run the Rune, blocking until the worker responds.
+    ///
+    /// See [`predict()`] for what happens inside the worker.
+    pub fn predict(
+        &mut self,
+        inputs: Vec<InputBinding>,
+        capture: Option<&Capture>,
+    ) -> Result<RuneOutputs, Error> {
+        let request = Request::Predict {
+            inputs,
+            capture: capture.cloned(),
+        };
+        self.worker.send(request)?;
+
+        match self.receive()? {
+            Response::Predicted(outputs) => Ok(outputs),
+            Response::Failed(e) => Err(e.into()),
+            Response::Loaded => Err(Error::msg("The worker sent an unexpected response")),
+        }
+    }
+
+    fn receive(&mut self) -> Result<Response, Error> {
+        read_message(&mut self.responses)
+            .context("Unable to read the worker's response")?
+            .context("The worker exited unexpectedly")
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        self.worker.kill();
+    }
+}
+
+/// A handle that can stop a worker from another thread.
+#[derive(Debug, Clone)]
+pub(crate) struct Worker {
+    process: Arc<Mutex<Child>>,
+    requests: Arc<Mutex<BufWriter<ChildStdin>>>,
+}
+
+impl Worker {
+    /// Ask the worker to stop the current run, failing with
+    /// [`RunFailed::Cancelled`].
+    ///
+    /// This only takes effect before or after the Rune's pipeline is run.
+    pub fn cancel(&self, run_id: Uuid) {
+        if let Err(e) = self.send(Request::Cancel { run_id }) {
+            tracing::debug!(
+                error = &*e as &dyn std::error::Error,
+                "Unable to cancel the run"
+            );
+        }
+    }
+
+    pub fn kill(&self) {
+        let mut child = self.process.lock().unwrap_or_else(PoisonError::into_inner);
+
+        // Errors just mean the worker has already exited
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+
+    /// Is the worker still able to handle requests?
+    pub fn is_running(&self) -> bool {
+        let mut child = self.process.lock().unwrap_or_else(PoisonError::into_inner);
+        matches!(child.try_wait(), Ok(None))
+    }
+
+    fn send(&self, request: Request) -> Result<(), Error> {
+        let mut requests = self.requests.lock().unwrap_or_else(PoisonError::into_inner);
+
+        write_message(&mut *requests, request)
+            .and_then(|_| requests.flush().map_err(Error::from))
+            .context("Unable to send a request to the worker")
+    }
+}
+
+/// The entrypoint for a worker process (see [`WORKER_FLAG`]).
+///
+/// Requests are handled one at a time until the parent process closes the
+/// worker's stdin.
+pub fn run_worker() -> Result<(), Error> {
+    let cancellation = Cancellation::default();
+    let (sender, requests) = mpsc::channel();
+
+    let reader = {
+        let cancellation = cancellation.clone();
+        std::thread::spawn(move || read_requests(&sender, &cancellation))
+    };
+
+    let stdout = std::io::stdout();
+    let mut responses = BufWriter::new(stdout.lock());
+    let mut loaded: Option<Loaded> = None;
+
+    for job in requests {
+        let result = match job {
+            Job::Load { rune } => Loaded::load(&rune).and_then(|l| {
+                loaded = Some(l);
+                // Loading can't be interrupted, so check once it is done
+                cancellation.check()?;
+                Ok(Response::Loaded)
+            }),
+            Job::Predict { inputs, capture } => match &mut loaded {
+                Some(loaded) => loaded
+                    .predict(inputs, capture.as_ref(), &cancellation)
+                    .map(Response::Predicted),
+                None => Err(Error::msg("No Rune has been loaded")),
+            },
+        };
+
+        let response = result.unwrap_or_else(|e| Response::Failed(WorkerError::from(&e)));
+        write_message(&mut responses, response)
+            .and_then(|_| responses.flush().map_err(Error::from))
+            .context("Unable to send the response")?;
+    }
+
+    reader
+        .join()
+        .map_err(|_| Error::msg("The reader thread panicked"))?
+}
+
+/// Read requests from stdin, passing them to the main thread.
+///
+/// Cancellations are handled immediately because the main thread will be
+/// busy running the Rune.
+fn read_requests(sender: &mpsc::Sender<Job>, cancellation: &Cancellation) -> Result<(), Error> {
+    let stdin = std::io::stdin();
+    let mut stdin = stdin.lock();
+
+    while let Some(request) = read_message(&mut stdin).context("Unable to read the request")? {
+        let job = match request {
+            Request::Cancel { run_id } => {
+                cancellation.cancel(run_id);
+                continue;
+            }
+            Request::Load { rune } => Job::Load { rune },
+            Request::Predict { inputs, capture } => Job::Predict { inputs, capture },
+        };
+
+        // A cancellation only applies to the request that was in progress
+        cancellation.reset();
+
+        if sender.send(job).is_err() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// A request for the main thread to handle.
+enum Job {
+    Load {
+        rune: Vec<u8>,
+    },
+    Predict {
+        inputs: Vec<InputBinding>,
+        capture: Option<Capture>,
+    },
+}
+
+/// Set when the parent process cancels the request that is being handled.
+#[derive(Debug, Default, Clone)]
+struct Cancellation(Arc<Mutex<Option<Uuid>>>);
+
+impl Cancellation {
+    fn cancel(&self, run_id: Uuid) {
+        *self.0.lock().unwrap_or_else(PoisonError::into_inner) = Some(run_id);
+    }
+
+    fn reset(&self) {
+        *self.0.lock().unwrap_or_else(PoisonError::into_inner) = None;
+    }
+
+    fn check(&self) -> Result<(), RunFailed> {
+        match *self.0.lock().unwrap_or_else(PoisonError::into_inner) {
+            Some(run_id) => Err(RunFailed::Cancelled { run_id }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The Rune a worker has loaded.
+struct Loaded {
+    engine: ZuneEngine,
+    signatures: InputSignatures,
+}
+
+impl Loaded {
+    fn load(rune: &[u8]) -> Result<Self, Error> {
+        let mut engine = ZuneEngine::load(rune).context("Unable to initialize the Zune engine")?;
+        let signatures = InputSignatures::load(&mut engine, rune)?;
+        tracing::info!(input_nodes = ?engine.input_nodes(), output_nodes = ?engine.output_nodes());
+
+        Ok(Loaded { engine, signatures })
+    }
+
+    fn predict(
+        &mut self,
+        inputs: Vec<InputBinding>,
+        capture: Option<&Capture>,
+        cancellation: &Cancellation,
+    ) -> Result<RuneOutputs, Error> {
+        let Loaded { engine, signatures } = self;
+
+        let outputs = predict(engine, signatures, inputs, capture, || cancellation.check())?;
+        // The pipeline can't be interrupted, so check once it is done
+        cancellation.check()?;
+
+        Ok(outputs)
+    }
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Request {
+    /// Load a Rune, which is sent as a raw frame.
+    Load {
+        #[serde(skip)]
+        rune: Vec<u8>,
+    },
+    Predict {
+        inputs: Vec<InputBinding>,
+        capture: Option<Capture>,
+    },
+    /// Stop the request that is in progress.
+    Cancel { run_id: Uuid },
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+enum Response {
+    Loaded,
+    Predicted(RuneOutputs),
+    Failed(WorkerError),
+}
+
+/// An error from inside the worker, keeping any [`RunFailed`] so the
+/// frontend still gets a typed error.
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+struct WorkerError {
+    message: String,
+    run_failed: Option<RunFailed>,
+}
+
+impl From<&Error> for WorkerError {
+    fn from(e: &Error) -> Self {
+        let run_failed = e
+            .downcast_ref::<RunFailed>()
+            .or_else(|| e.chain().find_map(|e| e.downcast_ref()))
+            .cloned();
+
+        WorkerError {
+            message: format!("{e:#}"),
+            run_failed,
+        }
+    }
+}
+
+impl From<WorkerError> for Error {
+    fn from(
+        WorkerError {
+            message,
+            run_failed,
+        }: WorkerError,
+    ) -> Self {
+        match run_failed {
+            Some(run_failed) if run_failed.to_string() == message => Error::from(run_failed),
+            Some(run_failed) => Error::from(run_failed).context(message),
+            None => Error::msg(message),
+        }
+    }
+}
+
+/// A message with byte buffers that are sent as raw frames.
+trait Buffers {
+    /// Every buffer in the message, in an order both ends agree on.
+    fn buffers(&mut self) -> Vec<&mut Vec<u8>>;
+}
+
+impl Buffers for Request {
+    fn buffers(&mut self) -> Vec<&mut Vec<u8>> {
+        match self {
+            Request::Load { rune } => vec![rune],
+            Request::Predict { inputs, .. } => inputs
+                .iter_mut()
+                .map(|binding| &mut binding.tensor.buffer)
+                .collect(),
+            Request::Cancel { .. } => Vec::new(),
+        }
+    }
+}
+
+impl Buffers for Response {
+    fn buffers(&mut self) -> Vec<&mut Vec<u8>> {
+        match self {
+            Response::Predicted(outputs) => {
+                // Sort the tensors so we don't depend on the hash maps'
+                // iteration order
+                let mut tensors: Vec<_> = outputs
+                    .nodes
+                    .iter_mut()
+                    .flat_map(|(node, tensors)| {
+                        tensors
+                            .iter_mut()
+                            .map(move |(name, tensor)| ((node, name), &mut tensor.buffer))
+                    })
+                    .collect();
+                tensors.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+                tensors.into_iter().map(|(_, buffer)| buffer).collect()
+            }
+            Response::Loaded | Response::Failed(_) => Vec::new(),
+        }
+    }
+}
+
+/// Write a message as a JSON frame followed by a raw frame for each of its
+/// buffers.
+fn write_message<M>(writer: &mut impl Write, mut message: M) -> Result<(), Error>
+where
+    M: Buffers + Serialize,
+{
+    let buffers: Vec<Vec<u8>> = message.buffers().into_iter().map(std::mem::take).collect();
+    let json = serde_json::to_vec(&message).context("Unable to serialize the message")?;
+
+    write_frame(writer, &json)?;
+    for buffer in &buffers {
+        write_frame(writer, buffer)?;
+    }
+
+    Ok(())
+}
+
+/// Read a message written by [`write_message()`], returning `None` if the
+/// other end has hung up.
+fn read_message<M>(reader: &mut impl Read) -> Result<Option<M>, Error>
+where
+    M: Buffers + DeserializeOwned,
+{
+    let frame = match read_frame(reader)? {
+        Some(frame) => frame,
+        None => return Ok(None),
+    };
+    let mut message: M = serde_json::from_slice(&frame).context("Unable to parse the message")?;
+
+    for buffer in message.buffers() {
+        *buffer = read_frame(reader)?.context("The message's buffers weren't sent")?;
+    }
+
+    Ok(Some(message))
+}
+
+fn write_frame(writer: &mut impl Write, bytes: &[u8]) -> std::io::Result<()> {
+    writer.write_all(&(bytes.len() as u64).to_le_bytes())?;
+    writer.write_all(bytes)
+}
+
+/// Read a frame, returning `None` if the other end has hung up.
+fn read_frame(reader: &mut impl Read) -> std::io::Result<Option<Vec<u8>>> {
+    let mut length = [0; 8];
+
+    match reader.read_exact(&mut length) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+
+    let mut frame = vec![0; u64::from_le_bytes(length) as usize];
+    reader.read_exact(&mut frame)?;
+
+    Ok(Some(frame))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, io::Cursor};
+
+    use super::*;
+    use crate::shared::{ElementType, Tensor};
+
+    #[test]
+    fn read_back_frames() {
+        let mut buffer = Vec::new();
+        write_frame(&mut buffer, b"first").unwrap();
+        write_frame(&mut buffer, b"").unwrap();
+
+        let mut reader = Cursor::new(buffer);
+
+        assert_eq!(read_frame(&mut reader).unwrap().unwrap(), b"first");
+        assert_eq!(read_frame(&mut reader).unwrap().unwrap(), b"");
+        assert!(read_frame(&mut reader).unwrap().is_none());
+    }
+
+    #[test]
+    fn send_buffers_as_raw_frames() {
+        let tensor = |buffer: &[u8]| Tensor {
+            element_type: ElementType::U8,
+            dimensions: vec![buffer.len()],
+            buffer: buffer.to_vec(),
+        };
+        let mut outputs = RuneOutputs::default();
+        outputs.nodes.insert(
+            "serial".to_string(),
+            HashMap::from([
+                ("a".to_string(), tensor(b"first")),
+                ("b".to_string(), tensor(b"second")),
+            ]),
+        );
+        outputs.nodes.insert(
+            "other".to_string(),
+            HashMap::from([("a".to_string(), tensor(b"third"))]),
+        );
+
+        let mut buffer = Vec::new();
+        write_message(&mut buffer, Response::Predicted(outputs.clone())).unwrap();
+
+        let mut reader = Cursor::new(buffer);
+        let json = read_frame(&mut reader).unwrap().unwrap();
+        assert!(String::from_utf8(json).unwrap().contains(r#""buffer":[]"#));
+        assert_eq!(read_frame(&mut reader).unwrap().unwrap(), b"third");
+
+        reader.set_position(0);
+        match read_message(&mut reader).unwrap().unwrap() {
+            Response::Predicted(received) => assert_eq!(received, outputs),
+            other => panic!("Unexpected response: {other:?}"),
+        }
+        assert!(read_message::<Response>(&mut reader).unwrap().is_none());
+    }
+
+    #[test]
+    fn only_cancel_the_request_in_progress() {
+        let cancellation = Cancellation::default();
+        let run_id = Uuid::new_v4();
+
+        cancellation.cancel(run_id);
+        assert_eq!(cancellation.check(), Err(RunFailed::Cancelled { run_id }));
+
+        cancellation.reset();
+        assert_eq!(cancellation.check(), Ok(()));
+    }
+
+    #[test]
+    fn errors_from_the_worker_keep_their_type() {
+        let original = Error::from(RunFailed::Timeout { limit_ms: 10 }).context("Running the Rune");
+
+        let json = serde_json::to_string(&WorkerError::from(&original)).unwrap();
+        let error = Error::from(serde_json::from_str::<WorkerError>(&json).unwrap());
+
+        assert_eq!(error.to_string(), format!("{original:#}"));
+        assert_eq!(
+            error.downcast_ref::<RunFailed>(),
+            Some(&RunFailed::Timeout { limit_ms: 10 })
+        );
+    }
+}
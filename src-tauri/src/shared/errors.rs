@@ -37,7 +37,7 @@ impl SerializableError {
     }
 }
 
-impl<E: std::error::Error + 'static> SerializableError<E> {
+impl<E: std::error::Error + Send + Sync + 'static> SerializableError<E> {
     /// If you know that somewhere inside the chain of errors is a particular
     /// type of error, you can use this [`SerializableError::downcast()`] method
     /// to serialize the error.
@@ -109,7 +109,11 @@ impl<E: std::error::Error + 'static> SerializableError<E> {
     }
 
     pub fn get_state(&self) -> Option<&E> {
-        self.error.chain().find_map(|e| e.downcast_ref::<E>())
+        // Errors attached using anyhow's context() aren't part of the chain,
+        // but the top-level error can still be downcast to them
+        self.error
+            .downcast_ref::<E>()
+            .or_else(|| self.error.chain().find_map(|e| e.downcast_ref::<E>()))
     }
 
     fn repr(&self) -> Repr<'_, E> {
@@ -139,7 +143,7 @@ where
     }
 }
 
-impl<E: Serialize + std::error::Error + 'static> Serialize for SerializableError<E> {
+impl<E: Serialize + std::error::Error + Send + Sync + 'static> Serialize for SerializableError<E> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
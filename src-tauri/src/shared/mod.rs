@@ -6,6 +6,7 @@
 mod arrow;
 mod errors;
 mod package;
+mod proc_blocks;
 mod runtime;
 mod tensors;
 
@@ -15,8 +16,17 @@ pub use self::{
     arrow::{DataType, Field, Schema},
     errors::SerializableError,
     package::Package,
-    runtime::{Analysis, ColumnMapping, Node, NodeKind, Pipeline},
-    tensors::{Dimensions, ElementType},
+    proc_blocks::{
+        ArgumentInfo, ArgumentType, AssetCacheStats, GarbageCollection, Incompatibility,
+        ProcBlockDiskUsage, ProcBlockSignature, ProcBlockSyncEvent, SyncSummary, TensorSignature,
+    },
+    runtime::{
+        Analysis, ColumnMapping, Node, NodeKind, NodeProfile, Pipeline, RunProfile, RuneOutputs,
+    },
+    tensors::{
+        tensors_from_record_batch, tensors_to_record_batch, DimensionMismatch, Dimensions,
+        ElementType, Tensor,
+    },
 };
 
 #[derive(Debug, Default, Clone, PartialEq, Eq, TS, serde::Serialize, serde::Deserialize)]
@@ -6,4 +6,8 @@ pub struct Package {
     pub description: String,
     pub last_version: String,
     pub public_url: String,
+    /// Was this package installed from a local file rather than downloaded
+    /// from the registry?
+    #[serde(default)]
+    pub local: bool,
 }
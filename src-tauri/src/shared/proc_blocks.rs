@@ -0,0 +1,187 @@
+use std::fmt::{self, Display, Formatter};
+
+use ts_rs::TS;
+
+use crate::shared::{DimensionMismatch, Dimensions, ElementType, Package};
+
+/// Everything we know about the interface a particular proc-block exposes.
+#[derive(Debug, Clone, PartialEq, Eq, TS, serde::Serialize, serde::Deserialize)]
+#[ts(export, export_to = "../src/backend/types/")]
+pub struct ProcBlockSignature {
+    pub name: String,
+    pub version: String,
+    pub description: Option<String>,
+    pub arguments: Vec<ArgumentInfo>,
+    pub inputs: Vec<TensorSignature>,
+    pub outputs: Vec<TensorSignature>,
+}
+
+/// An argument that can be passed to a proc-block.
+#[derive(Debug, Clone, PartialEq, Eq, TS, serde::Serialize, serde::Deserialize)]
+#[ts(export, export_to = "../src/backend/types/")]
+pub struct ArgumentInfo {
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(rename = "type")]
+    pub ty: ArgumentType,
+    /// The value used when this argument isn't provided, if any.
+    pub default_value: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, TS, serde::Serialize, serde::Deserialize)]
+#[ts(export, export_to = "../src/backend/types/")]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum ArgumentType {
+    Integer,
+    UnsignedInteger,
+    Float,
+    String,
+    LongString,
+    /// One of a fixed set of strings.
+    OneOf(Vec<String>),
+    /// The proc-block didn't say what type it expects.
+    Unknown,
+}
+
+/// A tensor that a proc-block consumes or produces.
+#[derive(Debug, Clone, PartialEq, Eq, TS, serde::Serialize, serde::Deserialize)]
+#[ts(export, export_to = "../src/backend/types/")]
+pub struct TensorSignature {
+    pub name: String,
+    pub description: Option<String>,
+    /// The element types this tensor may have. Empty when the proc-block
+    /// couldn't tell us (e.g. because it needs arguments we don't know yet).
+    pub element_types: Vec<ElementType>,
+    /// The tensor's shape, or [`Dimensions::Dynamic`] when it isn't known.
+    pub dimensions: Dimensions,
+}
+
+impl TensorSignature {
+    /// Check whether a tensor with this element type and shape could be
+    /// passed to this input, explaining why not if it can't.
+    pub fn accepts(
+        &self,
+        element_type: ElementType,
+        dimensions: &Dimensions,
+    ) -> Result<(), Incompatibility> {
+        if !self.element_types.is_empty() && !self.element_types.contains(&element_type) {
+            return Err(Incompatibility::ElementType {
+                expected: self.element_types.clone(),
+                actual: element_type,
+            });
+        }
+
+        self.dimensions
+            .check(dimensions)
+            .map_err(|reason| Incompatibility::Dimensions {
+                expected: self.dimensions.clone(),
+                actual: dimensions.clone(),
+                reason,
+            })
+    }
+}
+
+/// Why a tensor can't be passed to a proc-block's input.
+#[derive(Debug, Clone, PartialEq, Eq, TS, serde::Serialize, serde::Deserialize)]
+#[ts(export, export_to = "../src/backend/types/")]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum Incompatibility {
+    ElementType {
+        expected: Vec<ElementType>,
+        actual: ElementType,
+    },
+    Dimensions {
+        expected: Dimensions,
+        actual: Dimensions,
+        reason: DimensionMismatch,
+    },
+}
+
+impl Display for Incompatibility {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Incompatibility::ElementType { expected, actual } => {
+                let names: Vec<_> = expected.iter().map(|e| format!("{e:?}")).collect();
+                write!(
+                    f,
+                    "expected one of {} but found {actual:?}",
+                    names.join(", ")
+                )
+            }
+            Incompatibility::Dimensions {
+                expected,
+                actual,
+                reason,
+            } => write!(
+                f,
+                "expected dimensions {expected} but found {actual} ({reason})"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Incompatibility {}
+
+/// How often the compiler was able to load proc-blocks from the local cache.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, TS, serde::Serialize, serde::Deserialize)]
+#[ts(export, export_to = "../src/backend/types/")]
+pub struct AssetCacheStats {
+    pub hits: usize,
+    pub misses: usize,
+    /// May proc-blocks that aren't in the cache be downloaded?
+    pub allow_network: bool,
+}
+
+/// How much space a particular version of a proc-block takes up on disk.
+#[derive(Debug, Clone, PartialEq, Eq, TS, serde::Serialize, serde::Deserialize)]
+#[ts(export, export_to = "../src/backend/types/")]
+pub struct ProcBlockDiskUsage {
+    pub name: String,
+    pub version: String,
+    pub local: bool,
+    pub bytes: u64,
+}
+
+/// The result of removing unused proc-blocks from the cache.
+#[derive(Debug, Clone, PartialEq, Eq, TS, serde::Serialize, serde::Deserialize)]
+#[ts(export, export_to = "../src/backend/types/")]
+pub struct GarbageCollection {
+    /// The proc-blocks that were (or would have been, for a dry run) removed.
+    pub removed: Vec<ProcBlockDiskUsage>,
+    pub bytes_freed: u64,
+    pub dry_run: bool,
+}
+
+/// Events emitted while downloading proc-blocks from WAPM.
+#[derive(Debug, Clone, PartialEq, Eq, TS, serde::Serialize, serde::Deserialize)]
+#[ts(export, export_to = "../src/backend/types/")]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum ProcBlockSyncEvent {
+    /// We've found out which packages need to be downloaded.
+    Started { packages: Vec<Package> },
+    Progress {
+        name: String,
+        version: String,
+        downloaded_bytes: u64,
+        /// The package's size, if the server told us.
+        total_bytes: Option<u64>,
+    },
+    /// A package was downloaded and added to the cache.
+    Finished { name: String, version: String },
+    Failed {
+        name: String,
+        version: String,
+        error: String,
+    },
+    /// The sync is over, either because everything was downloaded or because
+    /// it was cancelled.
+    Completed(SyncSummary),
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, TS, serde::Serialize, serde::Deserialize)]
+#[ts(export, export_to = "../src/backend/types/")]
+pub struct SyncSummary {
+    pub downloaded: usize,
+    pub failed: usize,
+    pub cancelled: bool,
+}
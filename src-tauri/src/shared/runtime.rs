@@ -1,11 +1,59 @@
 use std::collections::HashMap;
 
 use ts_rs::TS;
+use uuid::Uuid;
+
+use crate::shared::Tensor;
 
 #[derive(Debug, Clone, PartialEq, Eq, TS, serde::Serialize, serde::Deserialize)]
 #[ts(export, export_to = "../src/backend/types/")]
 pub struct Analysis {
-    run_time_ms: u32,
+    pub run_time_ms: u32,
+    pub profile: RunProfile,
+}
+
+/// The tensors passed to every output node after running a Rune.
+#[derive(Debug, Default, Clone, PartialEq, Eq, TS, serde::Serialize, serde::Deserialize)]
+#[ts(export, export_to = "../src/backend/types/")]
+pub struct RuneOutputs {
+    /// The tensors, keyed by output node and tensor name.
+    pub nodes: HashMap<String, HashMap<String, Tensor>>,
+    /// Where the time went during the run.
+    pub profile: RunProfile,
+    /// The ID of the debug run every intermediate tensor was saved to, if
+    /// debugging was enabled.
+    pub debug_run: Option<Uuid>,
+}
+
+/// How long a Rune took to run and how much data was passed to each node.
+#[derive(Debug, Default, Clone, PartialEq, Eq, TS, serde::Serialize, serde::Deserialize)]
+#[ts(export, export_to = "../src/backend/types/")]
+pub struct RunProfile {
+    /// How long the whole run took, in microseconds.
+    #[ts(type = "number")]
+    pub elapsed_us: u64,
+    /// Statistics for each node, keyed by node name.
+    pub nodes: HashMap<String, NodeProfile>,
+}
+
+impl RunProfile {
+    /// Add another run's timings and data sizes to this one (e.g. when
+    /// running a Rune over a dataset in batches).
+    pub fn merge(&mut self, other: RunProfile) {
+        self.elapsed_us += other.elapsed_us;
+
+        for (name, node) in other.nodes {
+            let existing = self.nodes.entry(name).or_default();
+            existing.input_bytes += node.input_bytes;
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, TS, serde::Serialize, serde::Deserialize)]
+#[ts(export, export_to = "../src/backend/types/")]
+pub struct NodeProfile {
+    /// The total size of the tensors passed into this node.
+    pub input_bytes: usize,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, TS, serde::Serialize, serde::Deserialize)]
@@ -44,3 +92,36 @@ pub struct ColumnMapping {
     pub node_name: String,
     pub input_tensor_name: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_profiles_from_each_batch() {
+        let mut profile = RunProfile {
+            elapsed_us: 100,
+            nodes: HashMap::from([("argmax".to_string(), NodeProfile { input_bytes: 10 })]),
+        };
+        let other = RunProfile {
+            elapsed_us: 50,
+            nodes: HashMap::from([
+                ("argmax".to_string(), NodeProfile { input_bytes: 10 }),
+                ("model".to_string(), NodeProfile { input_bytes: 4 }),
+            ]),
+        };
+
+        profile.merge(other);
+
+        assert_eq!(
+            profile,
+            RunProfile {
+                elapsed_us: 150,
+                nodes: HashMap::from([
+                    ("argmax".to_string(), NodeProfile { input_bytes: 20 }),
+                    ("model".to_string(), NodeProfile { input_bytes: 4 }),
+                ]),
+            }
+        );
+    }
+}
@@ -1,3 +1,17 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+    sync::Arc,
+};
+
+use anyhow::{Context, Error};
+use arrow::{
+    array::{
+        Array, ArrayData, ArrayRef, BooleanArray, FixedSizeListArray, PrimitiveArray, StringArray,
+    },
+    datatypes::{self, DataType, Field, Schema},
+    record_batch::RecordBatch,
+};
 use ts_rs::TS;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize, TS)]
@@ -17,9 +31,675 @@ pub enum ElementType {
     Utf8,
 }
 
-#[derive(serde::Serialize, serde::Deserialize, TS)]
+impl ElementType {
+    /// Every element type.
+    pub const ALL: [ElementType; 11] = [
+        ElementType::U8,
+        ElementType::I8,
+        ElementType::U16,
+        ElementType::I16,
+        ElementType::U32,
+        ElementType::I32,
+        ElementType::F32,
+        ElementType::U64,
+        ElementType::I64,
+        ElementType::F64,
+        ElementType::Utf8,
+    ];
+
+    /// The number of bytes used by each element, or `None` for variable-length
+    /// types like strings.
+    pub fn size_of(self) -> Option<usize> {
+        match self {
+            ElementType::U8 | ElementType::I8 => Some(1),
+            ElementType::U16 | ElementType::I16 => Some(2),
+            ElementType::U32 | ElementType::I32 | ElementType::F32 => Some(4),
+            ElementType::U64 | ElementType::I64 | ElementType::F64 => Some(8),
+            ElementType::Utf8 => None,
+        }
+    }
+
+    /// The Arrow type used to store elements of this type.
+    pub fn arrow_type(self) -> DataType {
+        match self {
+            ElementType::U8 => DataType::UInt8,
+            ElementType::I8 => DataType::Int8,
+            ElementType::U16 => DataType::UInt16,
+            ElementType::I16 => DataType::Int16,
+            ElementType::U32 => DataType::UInt32,
+            ElementType::I32 => DataType::Int32,
+            ElementType::F32 => DataType::Float32,
+            ElementType::U64 => DataType::UInt64,
+            ElementType::I64 => DataType::Int64,
+            ElementType::F64 => DataType::Float64,
+            ElementType::Utf8 => DataType::Utf8,
+        }
+    }
+}
+
+/// A tensor passed into or read out of a Rune.
+///
+/// This is the canonical tensor representation used throughout the backend,
+/// and it can be converted losslessly to and from the runtime's
+/// [`TensorResult`](hotg_rune_runtime::zune::TensorResult), Arrow arrays (see
+/// [`Tensor::to_array()`]), and [`RecordBatch`]es (see
+/// [`tensors_to_record_batch()`]).
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, TS)]
+#[ts(export, export_to = "../src/backend/types/")]
+pub struct Tensor {
+    pub element_type: ElementType,
+    pub dimensions: Vec<usize>,
+    /// The tensor's elements, in little-endian byte order.
+    ///
+    /// Strings are stored one after the other, each prefixed by its length
+    /// in bytes as a little-endian `u32`.
+    pub buffer: Vec<u8>,
+}
+
+impl Tensor {
+    /// Create a string tensor.
+    pub fn from_strings<S: AsRef<str>>(
+        dimensions: Vec<usize>,
+        strings: impl IntoIterator<Item = S>,
+    ) -> Self {
+        let mut buffer = Vec::new();
+
+        for s in strings {
+            let s = s.as_ref();
+            buffer.extend((s.len() as u32).to_le_bytes());
+            buffer.extend(s.as_bytes());
+        }
+
+        Tensor {
+            element_type: ElementType::Utf8,
+            dimensions,
+            buffer,
+        }
+    }
+
+    /// Decode the elements of a string tensor.
+    pub fn strings(&self) -> Result<Vec<&str>, Error> {
+        if self.element_type != ElementType::Utf8 {
+            anyhow::bail!("Expected a string tensor, found {:?}", self.element_type);
+        }
+
+        let mut strings = Vec::new();
+        let mut rest = self.buffer.as_slice();
+
+        while !rest.is_empty() {
+            if rest.len() < 4 {
+                anyhow::bail!("The string tensor's buffer ends part-way through a length");
+            }
+            let (length, tail) = rest.split_at(4);
+            let length = u32::from_le_bytes(length.try_into().unwrap()) as usize;
+
+            if tail.len() < length {
+                anyhow::bail!("The string tensor's buffer ends part-way through a string");
+            }
+            let (s, tail) = tail.split_at(length);
+            strings.push(std::str::from_utf8(s).context("A string wasn't valid UTF-8")?);
+            rest = tail;
+        }
+
+        Ok(strings)
+    }
+
+    /// Convert an Arrow array into a tensor, where the first dimension is the
+    /// number of items in the array.
+    ///
+    /// Fixed-size lists (see [`Tensor::to_array()`]) add an extra dimension
+    /// for each level of nesting, and booleans are converted to `u8`s.
+    pub fn from_array(array: &dyn Array) -> Result<Self, Error> {
+        macro_rules! primitive {
+            ($arrow_type:ty, $element_type:ident) => {{
+                let array = array
+                    .as_any()
+                    .downcast_ref::<PrimitiveArray<$arrow_type>>()
+                    .expect("Already checked the data type");
+                let buffer = array
+                    .values()
+                    .iter()
+                    .flat_map(|value| value.to_le_bytes())
+                    .collect();
+                (ElementType::$element_type, buffer)
+            }};
+        }
+
+        if array.null_count() > 0 {
+            anyhow::bail!("Tensors can't contain null values");
+        }
+
+        let (element_type, buffer) = match array.data_type() {
+            DataType::UInt8 => primitive!(datatypes::UInt8Type, U8),
+            DataType::Int8 => primitive!(datatypes::Int8Type, I8),
+            DataType::UInt16 => primitive!(datatypes::UInt16Type, U16),
+            DataType::Int16 => primitive!(datatypes::Int16Type, I16),
+            DataType::UInt32 => primitive!(datatypes::UInt32Type, U32),
+            DataType::Int32 => primitive!(datatypes::Int32Type, I32),
+            DataType::Float32 => primitive!(datatypes::Float32Type, F32),
+            DataType::UInt64 => primitive!(datatypes::UInt64Type, U64),
+            DataType::Int64 => primitive!(datatypes::Int64Type, I64),
+            DataType::Float64 => primitive!(datatypes::Float64Type, F64),
+            DataType::Boolean => {
+                let array = array
+                    .as_any()
+                    .downcast_ref::<BooleanArray>()
+                    .expect("Already checked the data type");
+                let buffer = (0..array.len()).map(|i| array.value(i) as u8).collect();
+                (ElementType::U8, buffer)
+            }
+            DataType::Utf8 => {
+                let array = array
+                    .as_any()
+                    .downcast_ref::<StringArray>()
+                    .expect("Already checked the data type");
+                let strings = (0..array.len()).map(|i| array.value(i));
+                return Ok(Tensor::from_strings(vec![array.len()], strings));
+            }
+            DataType::FixedSizeList(_, size) => {
+                let list = array
+                    .as_any()
+                    .downcast_ref::<FixedSizeListArray>()
+                    .expect("Already checked the data type");
+                // Note: the child array isn't sliced along with the list
+                let values = list
+                    .values()
+                    .slice(list.value_offset(0) as usize, list.len() * *size as usize);
+                let inner = Tensor::from_array(values.as_ref())?;
+
+                let mut dimensions = vec![list.len(), *size as usize];
+                dimensions.extend(inner.dimensions.iter().skip(1));
+
+                return Ok(Tensor {
+                    dimensions,
+                    ..inner
+                });
+            }
+            other => anyhow::bail!("Unable to convert a {other} column into a tensor"),
+        };
+
+        Ok(Tensor {
+            element_type,
+            dimensions: vec![array.len()],
+            buffer,
+        })
+    }
+
+    /// Convert the tensor into an Arrow array with one item for each element
+    /// along the first dimension.
+    ///
+    /// Each of the remaining dimensions becomes a level of fixed-size lists,
+    /// so a `[2, 3, 4]` tensor is an array of 2 lists, each containing 3
+    /// lists of 4 elements. Scalars (tensors with no dimensions) become an
+    /// array with a single item.
+    pub fn to_array(&self) -> Result<ArrayRef, Error> {
+        macro_rules! primitive {
+            ($arrow_type:ty, $native:ty) => {{
+                const SIZE: usize = std::mem::size_of::<$native>();
+                if self.buffer.len() % SIZE != 0 {
+                    anyhow::bail!(
+                        "A {:?} tensor's buffer should be a multiple of {} bytes, but it is {} bytes long",
+                        self.element_type,
+                        SIZE,
+                        self.buffer.len()
+                    );
+                }
+                let values = self.buffer.chunks_exact(SIZE).map(|chunk| {
+                    let mut bytes = [0; SIZE];
+                    bytes.copy_from_slice(chunk);
+                    <$native>::from_le_bytes(bytes)
+                });
+                Arc::new(PrimitiveArray::<$arrow_type>::from_iter_values(values)) as ArrayRef
+            }};
+        }
+
+        let mut values = match self.element_type {
+            ElementType::U8 => primitive!(datatypes::UInt8Type, u8),
+            ElementType::I8 => primitive!(datatypes::Int8Type, i8),
+            ElementType::U16 => primitive!(datatypes::UInt16Type, u16),
+            ElementType::I16 => primitive!(datatypes::Int16Type, i16),
+            ElementType::U32 => primitive!(datatypes::UInt32Type, u32),
+            ElementType::I32 => primitive!(datatypes::Int32Type, i32),
+            ElementType::F32 => primitive!(datatypes::Float32Type, f32),
+            ElementType::U64 => primitive!(datatypes::UInt64Type, u64),
+            ElementType::I64 => primitive!(datatypes::Int64Type, i64),
+            ElementType::F64 => primitive!(datatypes::Float64Type, f64),
+            ElementType::Utf8 => Arc::new(StringArray::from_iter_values(self.strings()?)),
+        };
+
+        let element_count: usize = self.dimensions.iter().product();
+        if values.len() != element_count {
+            anyhow::bail!(
+                "A tensor with dimensions {:?} should have {} elements, but its buffer contains {}",
+                self.dimensions,
+                element_count,
+                values.len()
+            );
+        }
+
+        // Wrap the flattened values in lists, starting from the innermost
+        // dimension
+        for (i, &size) in self.dimensions.iter().enumerate().skip(1).rev() {
+            let len: usize = self.dimensions[..i].iter().product();
+            let data = ArrayData::builder(DataType::FixedSizeList(
+                Box::new(Field::new("item", values.data_type().clone(), false)),
+                size as i32,
+            ))
+            .len(len)
+            .add_child_data(values.data().clone())
+            .build()
+            .context("Unable to create a fixed-size list")?;
+
+            values = Arc::new(FixedSizeListArray::from(data));
+        }
+
+        Ok(values)
+    }
+}
+
+/// The field metadata used to remember a tensor's rank, so scalars (which
+/// become an array with a single item) can be told apart from tensors with
+/// one element.
+const RANK_METADATA: &str = "tensor_rank";
+
+/// Put several tensors into a [`RecordBatch`], with one column per tensor.
+///
+/// Each tensor is converted using [`Tensor::to_array()`], so they must all
+/// have the same first dimension. Each field records its tensor's rank, which
+/// [`tensors_from_record_batch()`] uses to turn scalars back into scalars.
+pub fn tensors_to_record_batch<'a>(
+    tensors: impl IntoIterator<Item = (&'a str, &'a Tensor)>,
+) -> Result<RecordBatch, Error> {
+    let mut fields = Vec::new();
+    let mut columns = Vec::new();
+
+    for (name, tensor) in tensors {
+        let array = tensor
+            .to_array()
+            .with_context(|| format!("Unable to convert \"{name}\" to an Arrow array"))?;
+        let metadata = BTreeMap::from([(
+            RANK_METADATA.to_string(),
+            tensor.dimensions.len().to_string(),
+        )]);
+        fields
+            .push(Field::new(name, array.data_type().clone(), false).with_metadata(Some(metadata)));
+        columns.push(array);
+    }
+
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)
+        .context("Unable to create a record batch from the tensors")
+}
+
+/// Convert each column in a [`RecordBatch`] into a tensor (see
+/// [`Tensor::from_array()`]), returning them in order alongside the column
+/// names.
+pub fn tensors_from_record_batch(batch: &RecordBatch) -> Result<Vec<(String, Tensor)>, Error> {
+    batch
+        .schema()
+        .fields()
+        .iter()
+        .zip(batch.columns())
+        .map(|(field, column)| {
+            let tensor = column_to_tensor(field, column.as_ref())
+                .with_context(|| format!("Unable to convert the \"{}\" column", field.name()))?;
+            Ok((field.name().clone(), tensor))
+        })
+        .collect()
+}
+
+fn column_to_tensor(field: &Field, column: &dyn Array) -> Result<Tensor, Error> {
+    let mut tensor = Tensor::from_array(column)?;
+
+    let rank = match field.metadata().and_then(|m| m.get(RANK_METADATA)) {
+        Some(rank) => rank
+            .parse::<usize>()
+            .with_context(|| format!("\"{rank}\" isn't a valid tensor rank"))?,
+        None => return Ok(tensor),
+    };
+
+    if rank == 0 && tensor.dimensions == [1] {
+        tensor.dimensions.clear();
+    } else if rank != tensor.dimensions.len() {
+        anyhow::bail!(
+            "Expected a tensor with rank {rank}, but found one with dimensions {:?}",
+            tensor.dimensions
+        );
+    }
+
+    Ok(tensor)
+}
+
+impl From<hotg_rune_runtime::zune::TensorResult> for Tensor {
+    fn from(t: hotg_rune_runtime::zune::TensorResult) -> Self {
+        Tensor {
+            element_type: t.element_type.into(),
+            dimensions: t.dimensions.into_iter().map(|d| d as usize).collect(),
+            buffer: t.buffer,
+        }
+    }
+}
+
+impl From<Tensor> for hotg_rune_runtime::zune::TensorResult {
+    fn from(t: Tensor) -> Self {
+        hotg_rune_runtime::zune::TensorResult {
+            element_type: t.element_type.into(),
+            dimensions: t.dimensions.into_iter().map(|d| d as u32).collect(),
+            buffer: t.buffer,
+        }
+    }
+}
+
+/// The shape of a tensor.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize, TS)]
 #[ts(export, export_to = "../src/backend/types/")]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
 pub enum Dimensions {
+    /// The tensor could have any shape.
     Dynamic,
-    Fixed(Vec<usize>),
+    /// The tensor has a known number of dimensions, where `None` (written as
+    /// `?`) means that dimension can be any length.
+    Fixed(Vec<Option<usize>>),
+}
+
+impl Dimensions {
+    /// Create [`Dimensions`] using the convention from Runefiles and
+    /// proc-blocks, where a length of `0` means "any length".
+    pub fn with_wildcards(dimensions: &[usize]) -> Self {
+        Dimensions::Fixed(
+            dimensions
+                .iter()
+                .map(|&d| if d == 0 { None } else { Some(d) })
+                .collect(),
+        )
+    }
+
+    /// Check whether a tensor with the `actual` shape could be used where
+    /// this shape is expected.
+    ///
+    /// Wildcards on either side match any length and dynamic shapes match
+    /// everything, so this only fails when the two shapes definitely aren't
+    /// compatible.
+    pub fn check(&self, actual: &Dimensions) -> Result<(), DimensionMismatch> {
+        let (expected, actual) = match (self, actual) {
+            (Dimensions::Fixed(expected), Dimensions::Fixed(actual)) => (expected, actual),
+            _ => return Ok(()),
+        };
+
+        if expected.len() != actual.len() {
+            return Err(DimensionMismatch::Rank {
+                expected: expected.len(),
+                actual: actual.len(),
+            });
+        }
+
+        for (dimension, (e, a)) in expected.iter().zip(actual).enumerate() {
+            if let (Some(expected), Some(actual)) = (*e, *a) {
+                if expected != actual {
+                    return Err(DimensionMismatch::Length {
+                        dimension,
+                        expected,
+                        actual,
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl From<&[usize]> for Dimensions {
+    fn from(dimensions: &[usize]) -> Self {
+        Dimensions::Fixed(dimensions.iter().copied().map(Some).collect())
+    }
+}
+
+impl Display for Dimensions {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Dimensions::Dynamic => write!(f, "[..]"),
+            Dimensions::Fixed(dimensions) => {
+                write!(f, "[")?;
+                for (i, dimension) in dimensions.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    match dimension {
+                        Some(length) => write!(f, "{length}")?,
+                        None => write!(f, "?")?,
+                    }
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+/// Why one shape can't be used where another is expected (see
+/// [`Dimensions::check()`]).
+#[derive(Debug, Clone, PartialEq, Eq, TS, serde::Serialize, serde::Deserialize)]
+#[ts(export, export_to = "../src/backend/types/")]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum DimensionMismatch {
+    /// The shapes have a different number of dimensions.
+    Rank { expected: usize, actual: usize },
+    /// A dimension has the wrong length.
+    Length {
+        dimension: usize,
+        expected: usize,
+        actual: usize,
+    },
+}
+
+impl Display for DimensionMismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DimensionMismatch::Rank { expected, actual } => {
+                write!(f, "expected {expected} dimensions but found {actual}")
+            }
+            DimensionMismatch::Length {
+                dimension,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "dimension {dimension} should have a length of {expected} but it is {actual}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DimensionMismatch {}
+
+impl From<hotg_rune_runtime::zune::ElementType> for ElementType {
+    fn from(e: hotg_rune_runtime::zune::ElementType) -> Self {
+        use hotg_rune_runtime::zune::ElementType as E;
+
+        match e {
+            E::U8 => ElementType::U8,
+            E::I8 => ElementType::I8,
+            E::U16 => ElementType::U16,
+            E::I16 => ElementType::I16,
+            E::U32 => ElementType::U32,
+            E::I32 => ElementType::I32,
+            E::F32 => ElementType::F32,
+            E::U64 => ElementType::U64,
+            E::I64 => ElementType::I64,
+            E::F64 => ElementType::F64,
+            E::Utf8 => ElementType::Utf8,
+        }
+    }
+}
+
+impl From<ElementType> for hotg_rune_runtime::zune::ElementType {
+    fn from(e: ElementType) -> Self {
+        use hotg_rune_runtime::zune::ElementType as E;
+
+        match e {
+            ElementType::U8 => E::U8,
+            ElementType::I8 => E::I8,
+            ElementType::U16 => E::U16,
+            ElementType::I16 => E::I16,
+            ElementType::U32 => E::U32,
+            ElementType::I32 => E::I32,
+            ElementType::F32 => E::F32,
+            ElementType::U64 => E::U64,
+            ElementType::I64 => E::I64,
+            ElementType::F64 => E::F64,
+            ElementType::Utf8 => E::Utf8,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::RangeInclusive;
+
+    use hotg_rune_runtime::zune::TensorResult;
+    use proptest::prelude::*;
+
+    use super::*;
+
+    /// Generate a tensor with up to 3 dimensions.
+    fn tensor(element_type: ElementType, ranks: RangeInclusive<usize>) -> BoxedStrategy<Tensor> {
+        prop::collection::vec(0_usize..4, ranks)
+            .prop_flat_map(move |dimensions| {
+                let count: usize = dimensions.iter().product();
+
+                match element_type.size_of() {
+                    Some(size) => prop::collection::vec(any::<u8>(), count * size)
+                        .prop_map(move |buffer| Tensor {
+                            element_type,
+                            dimensions: dimensions.clone(),
+                            buffer,
+                        })
+                        .boxed(),
+                    None => prop::collection::vec(".*", count)
+                        .prop_map(move |strings| Tensor::from_strings(dimensions.clone(), strings))
+                        .boxed(),
+                }
+            })
+            .boxed()
+    }
+
+    /// Generate one tensor for each [`ElementType`], including scalars.
+    fn every_element_type() -> Vec<BoxedStrategy<Tensor>> {
+        ElementType::ALL
+            .into_iter()
+            .map(|ty| tensor(ty, 0..=3))
+            .collect()
+    }
+
+    /// A bare Arrow array can't tell a scalar apart from a tensor with a
+    /// single element, so only generate tensors with at least one dimension.
+    fn every_element_type_with_dimensions() -> Vec<BoxedStrategy<Tensor>> {
+        ElementType::ALL
+            .into_iter()
+            .map(|ty| tensor(ty, 1..=3))
+            .collect()
+    }
+
+    proptest! {
+        #[test]
+        fn round_trip_through_tensor_result(tensors in every_element_type()) {
+            for tensor in tensors {
+                let round_tripped = Tensor::from(TensorResult::from(tensor.clone()));
+
+                prop_assert_eq!(round_tripped, tensor);
+            }
+        }
+
+        #[test]
+        fn round_trip_through_arrow(tensors in every_element_type_with_dimensions()) {
+            for tensor in tensors {
+                let array = tensor.to_array().unwrap();
+
+                let round_tripped = Tensor::from_array(array.as_ref()).unwrap();
+
+                prop_assert_eq!(round_tripped, tensor);
+            }
+        }
+
+        #[test]
+        fn round_trip_through_record_batch(tensors in every_element_type()) {
+            for tensor in tensors {
+                let batch = tensors_to_record_batch([("tensor", &tensor)]).unwrap();
+
+                let round_tripped = tensors_from_record_batch(&batch).unwrap();
+
+                prop_assert_eq!(round_tripped, vec![("tensor".to_string(), tensor)]);
+            }
+        }
+    }
+
+    #[test]
+    fn scalars_keep_their_rank() {
+        let scalar = Tensor {
+            element_type: ElementType::F32,
+            dimensions: Vec::new(),
+            buffer: 1.5_f32.to_le_bytes().to_vec(),
+        };
+
+        let batch = tensors_to_record_batch([("scalar", &scalar)]).unwrap();
+        let round_tripped = tensors_from_record_batch(&batch).unwrap();
+
+        assert_eq!(batch.num_rows(), 1);
+        assert_eq!(round_tripped, vec![("scalar".to_string(), scalar)]);
+    }
+
+    #[test]
+    fn wildcards_match_any_length() {
+        let expected = Dimensions::with_wildcards(&[0, 4]);
+
+        assert_eq!(expected.to_string(), "[?, 4]");
+        assert!(expected.check(&Dimensions::from(&[3, 4][..])).is_ok());
+        assert!(expected.check(&Dimensions::Fixed(vec![None, None])).is_ok());
+        assert!(expected.check(&Dimensions::Dynamic).is_ok());
+        assert_eq!(
+            expected.check(&Dimensions::from(&[3, 5][..])),
+            Err(DimensionMismatch::Length {
+                dimension: 1,
+                expected: 4,
+                actual: 5
+            })
+        );
+        assert_eq!(
+            expected.check(&Dimensions::from(&[4][..])),
+            Err(DimensionMismatch::Rank {
+                expected: 2,
+                actual: 1
+            })
+        );
+    }
+
+    #[test]
+    fn nest_extra_dimensions_as_fixed_size_lists() {
+        let tensor = Tensor {
+            element_type: ElementType::U8,
+            dimensions: vec![2, 3, 4],
+            buffer: (0..24).collect(),
+        };
+
+        let array = tensor.to_array().unwrap();
+
+        assert_eq!(array.len(), 2);
+        assert_eq!(
+            array.data_type(),
+            &DataType::FixedSizeList(
+                Box::new(Field::new(
+                    "item",
+                    DataType::FixedSizeList(
+                        Box::new(Field::new("item", DataType::UInt8, false)),
+                        4
+                    ),
+                    false
+                )),
+                3
+            )
+        );
+        // slicing shouldn't affect the elements we read back
+        let second = Tensor::from_array(array.slice(1, 1).as_ref()).unwrap();
+        assert_eq!(second.dimensions, vec![1, 3, 4]);
+        assert_eq!(second.buffer, (12..24).collect::<Vec<u8>>());
+    }
 }
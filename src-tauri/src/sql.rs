@@ -4,8 +4,12 @@ use std::{
 };
 
 use arrow::{ipc::writer::StreamWriter, record_batch::RecordBatch};
+use uuid::Uuid;
 
-use crate::{shared::SerializableError, AppState};
+use crate::{shared::SerializableError, AppState, BlobStore};
+
+/// The content type used for Arrow IPC streams in the [`BlobStore`].
+pub(crate) const ARROW_CONTENT_TYPE: &str = "application/vnd.apache.arrow.stream";
 
 /// Check whether a particular SQL statement is valid and get back the first
 /// couple records.
@@ -16,6 +20,7 @@ use crate::{shared::SerializableError, AppState};
 #[tracing::instrument(skip_all)]
 pub async fn validate_sql(
     app: tauri::State<'_, AppState>,
+    blobs: tauri::State<'_, BlobStore>,
     sql: &str,
     //_max_rows: usize,
 ) -> Result<ValidationResponse, SerializableError<ValidationFailed>> {
@@ -39,7 +44,7 @@ pub async fn validate_sql(
 
     Ok(ValidationResponse {
         row_count,
-        preview: serialize_preview(&record_batch)?,
+        preview: blobs.insert(ARROW_CONTENT_TYPE, serialize_preview(&record_batch)?),
     })
 }
 
@@ -92,7 +97,7 @@ impl Display for ValidationFailed {
 pub struct ValidationResponse {
     /// The total number of rows that were selected.
     pub row_count: usize,
-    /// A preview of the first N records, serialized as an Apache Arrow array
-    /// using their IPC format.
-    pub preview: Vec<u8>,
+    /// The ID of a blob containing a preview of the first N records,
+    /// serialized as an Apache Arrow array using their IPC format.
+    pub preview: Uuid,
 }
@@ -53,7 +53,7 @@ pub async fn known_proc_blocks(
     ];
     let packages: Vec<Package> = packages
         .iter()
-        .filter(|p| white_list.contains(&p.get("name").unwrap().as_str().unwrap()))
+        .filter(|p| white_list.contains(&p.get("name").unwrap().as_str().unwrap()) || is_local(p))
         .map(|record: &serde_json::Map<String, serde_json::Value>| {
             let name = record.get("name").unwrap().as_str().unwrap().to_string();
 
@@ -65,11 +65,13 @@ pub async fn known_proc_blocks(
                 .to_string();
             let version = record.get("version").unwrap().as_str().unwrap().to_string();
             let public_url = record.get("fileLoc").unwrap().as_str().unwrap().to_string();
+            let local = is_local(record);
             Package {
                 name,
                 description,
                 last_version: version,
                 public_url,
+                local,
             }
         })
         .collect();
@@ -83,9 +85,7 @@ pub async fn known_proc_blocks(
 }
 
 #[tracing::instrument(skip_all, err)]
-pub async fn fetch_packages(
-    client: tauri::State<'_, reqwest::Client>,
-) -> Result<Vec<Package>, SerializableError> {
+pub async fn fetch_packages(client: &reqwest::Client) -> Result<Vec<Package>, SerializableError> {
     let query = GetNamespace::build_query(get_namespace::Variables {
         name: "hotg-ai".to_string(),
     });
@@ -162,6 +162,14 @@ impl Display for SerializableError {
 
 impl std::error::Error for SerializableError {}
 
+/// Was this `proc_blocks` record sideloaded by the user?
+fn is_local(record: &serde_json::Map<String, serde_json::Value>) -> bool {
+    record
+        .get("local")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+}
+
 fn flatten_packages(data: Option<get_namespace::ResponseData>) -> Vec<Package> {
     let edges = data
         .and_then(|d| d.get_namespace)
@@ -184,6 +192,7 @@ fn flatten_packages(data: Option<get_namespace::ResponseData>) -> Vec<Package> {
                 description: last_version.description,
                 last_version: last_version.version,
                 public_url: main_module.public_url,
+                local: false,
             };
             packages.push(pkg);
         }
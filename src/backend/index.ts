@@ -11,6 +11,31 @@ import { DatasetPage } from "./types/DatasetPage";
 import { Pipeline } from "./types/Pipeline";
 import { ColumnMapping } from "./types/ColumnMapping";
 import { Analysis } from "./types/Analysis";
+import { ProcBlockSignature } from "./types/ProcBlockSignature";
+import { AssetCacheStats } from "./types/AssetCacheStats";
+import { ProcBlockDiskUsage } from "./types/ProcBlockDiskUsage";
+import { GarbageCollection } from "./types/GarbageCollection";
+import { SyncSummary } from "./types/SyncSummary";
+import { CompileFailed } from "./types/CompileFailed";
+import { CompileResponse } from "./types/CompileResponse";
+import { ClearedCache } from "./types/ClearedCache";
+import { ProjectSettings } from "./types/ProjectSettings";
+import { Diagnostic } from "./types/Diagnostic";
+import { RuneDescription } from "./types/RuneDescription";
+import { RuneManifest } from "./types/RuneManifest";
+import { ImportedRune } from "./types/ImportedRune";
+import { MissingProcBlocks } from "./types/MissingProcBlocks";
+import { Schema } from "./types/Schema";
+import { RunFailed } from "./types/RunFailed";
+import { InputBinding } from "./types/InputBinding";
+import { DatasetRunSummary } from "./types/DatasetRunSummary";
+import { RuneOutputs } from "./types/RuneOutputs";
+import { TensorInfo } from "./types/TensorInfo";
+import { TensorSignature } from "./types/TensorSignature";
+import { Incompatibility } from "./types/Incompatibility";
+import { RunLimits } from "./types/RunLimits";
+import { DebugRun } from "./types/DebugRun";
+import { TensorDirection } from "./types/TensorDirection";
 
 export type ValidationResponse = {
   numRows: number;
@@ -47,10 +72,12 @@ export async function validate_sql(sql: string, _maxRows: number = 10): Promise<
 
     try {
         const {row_count, preview}: RawValidationResponse = await invoke("validate_sql", { sql });
-       
+        const bytes = await fetch_blob(preview);
+        await release_blob(preview);
+
         return ok({
             numRows: row_count,
-            preview: tableFromIPC(Uint8Array.from(preview))
+            preview: tableFromIPC(bytes)
         });
     } catch(e) {
         return err(is_serializable_error(e) ? e : to_serializable_error(e));
@@ -138,10 +165,24 @@ export async function read_dataset_page(
   }
 }
 
+/**
+ * Check whether a dataset's tensor could be passed to a node's input.
+ *
+ * Wildcard dimensions (e.g. the `?` in `[?, 4]`) match any length.
+ *
+ * @returns `null` if the tensor is compatible, otherwise the reason it isn't.
+ */
+export async function check_tensor_compatibility(
+  tensor: TensorInfo,
+  input: TensorSignature
+): Promise<Incompatibility | null> {
+  return await invoke("check_tensor_compatibility", { tensor, input });
+}
+
 export async function execute_analysis(
   pipeline: Pipeline,
   column_mapping: ColumnMapping
-): Promise<Result<Analysis>> {
+): Promise<Result<Analysis, SerializableError<RunFailed>>> {
   try {
     const response = await invoke("execute_analysis", {
       pipeline,
@@ -160,6 +201,537 @@ export async function known_proc_blocks(): Promise<Package[]> {
   return await invoke("known_proc_blocks");
 }
 
+/**
+ * Find out which arguments a cached proc-block takes and which tensors it
+ * consumes and produces.
+ *
+ * @param name The proc-block's name (e.g. "hotg-ai/argmax").
+ * @param version The version to inspect.
+ */
+export async function proc_block_signature(
+  name: string,
+  version: string
+): Promise<Result<ProcBlockSignature>> {
+  try {
+    const response = await invoke("proc_block_signature", { name, version });
+    return ok(response as ProcBlockSignature);
+  } catch (e) {
+    return err(is_serializable_error(e) ? e : to_serializable_error(e));
+  }
+}
+
+/**
+ * Install a proc-block from a `*.wasm` file on disk.
+ *
+ * @param path The `*.wasm` file to install.
+ * @param name Install under a different name (e.g. `"my-team/normalize"`)
+ * instead of the one the proc-block gives itself.
+ * @param overwrite Replace the version in the cache if it is already
+ * installed.
+ */
+export async function install_proc_block_from_file(
+  path: string,
+  name?: string,
+  overwrite?: boolean
+): Promise<Result<Package>> {
+  try {
+    const response = await invoke("install_proc_block_from_file", {
+      path,
+      name,
+      overwrite,
+    });
+    return ok(response as Package);
+  } catch (e) {
+    return err(is_serializable_error(e) ? e : to_serializable_error(e));
+  }
+}
+
+/**
+ * Find out how often the compiler was able to use the local proc-block cache.
+ */
+export async function asset_cache_stats(): Promise<AssetCacheStats> {
+  return await invoke("asset_cache_stats");
+}
+
+/**
+ * Control whether the compiler may download proc-blocks that aren't in the
+ * local cache.
+ */
+export async function set_allow_network(
+  allowNetwork: boolean
+): Promise<AssetCacheStats> {
+  return await invoke("set_allow_network", { allowNetwork });
+}
+
+/**
+ * Remove a particular version of a proc-block from the local cache.
+ */
+export async function uninstall_proc_block(
+  name: string,
+  version: string
+): Promise<Result<ProcBlockDiskUsage>> {
+  try {
+    const response = await invoke("uninstall_proc_block", { name, version });
+    return ok(response as ProcBlockDiskUsage);
+  } catch (e) {
+    return err(is_serializable_error(e) ? e : to_serializable_error(e));
+  }
+}
+
+/**
+ * Show how much disk space each cached proc-block is using.
+ */
+export async function proc_block_disk_usage(): Promise<
+  Result<ProcBlockDiskUsage[]>
+> {
+  try {
+    const response = await invoke("proc_block_disk_usage");
+    return ok(response as ProcBlockDiskUsage[]);
+  } catch (e) {
+    return err(is_serializable_error(e) ? e : to_serializable_error(e));
+  }
+}
+
+/**
+ * Delete every cached proc-block that isn't used by a project, either in the
+ * last Runefile it compiled or its saved pipeline (see `save_pipeline()`).
+ *
+ * @param dryRun Report what would be removed without deleting anything.
+ */
+export async function collect_proc_block_garbage(
+  dryRun: boolean = false
+): Promise<Result<GarbageCollection>> {
+  try {
+    const response = await invoke("collect_proc_block_garbage", { dryRun });
+    return ok(response as GarbageCollection);
+  } catch (e) {
+    return err(is_serializable_error(e) ? e : to_serializable_error(e));
+  }
+}
+
+/**
+ * Download any proc-blocks that aren't already in the local cache.
+ *
+ * Listen for `proc_block_sync_progress` events (see `ProcBlockSyncEvent`) to
+ * follow along.
+ *
+ * @param concurrency How many packages to download at a time.
+ */
+export async function sync_proc_blocks(
+  concurrency?: number
+): Promise<Result<SyncSummary>> {
+  try {
+    const response = await invoke("sync_proc_blocks", { concurrency });
+    return ok(response as SyncSummary);
+  } catch (e) {
+    return err(is_serializable_error(e) ? e : to_serializable_error(e));
+  }
+}
+
+/**
+ * Stop the proc-block sync that is currently running.
+ *
+ * @returns `false` if there was nothing to cancel.
+ */
+export async function cancel_proc_block_sync(): Promise<boolean> {
+  return await invoke("cancel_proc_block_sync");
+}
+
+/**
+ * Compile a Runefile into a Rune, using the settings saved for the named
+ * project.
+ *
+ * The compiler remembers previous compilations, so only the parts of the
+ * Runefile which changed will need to be rebuilt.
+ *
+ * Listen for `compilation_progress` events (see `CompilationEvent`) to follow
+ * along as each phase of the compilation runs.
+ *
+ * If compilation fails, the error will contain diagnostics pointing at the
+ * parts of the Runefile that caused the problem.
+ */
+export async function compile(
+  runefile: string,
+  project: string
+): Promise<Result<CompileResponse, SerializableError<CompileFailed>>> {
+  try {
+    const response = await invoke("compile", { runefile, project });
+    return ok(response as CompileResponse);
+  } catch (e) {
+    return err(is_serializable_error(e) ? e : to_serializable_error(e));
+  }
+}
+
+/**
+ * Check a Runefile for problems (unknown stages, mismatched tensors, missing
+ * arguments, etc.) without compiling it.
+ */
+export async function check_runefile(
+  runefile: string,
+  project: string
+): Promise<Result<Diagnostic[]>> {
+  try {
+    const response = await invoke("check_runefile", { runefile, project });
+    return ok(response as Diagnostic[]);
+  } catch (e) {
+    return err(is_serializable_error(e) ? e : to_serializable_error(e));
+  }
+}
+
+/**
+ * Describe the nodes, tensors and resources inside a compiled Rune.
+ */
+export async function inspect_rune(
+  rune: string
+): Promise<Result<RuneDescription>> {
+  try {
+    const response = await invoke("inspect_rune", { rune });
+    return ok(response as RuneDescription);
+  } catch (e) {
+    return err(is_serializable_error(e) ? e : to_serializable_error(e));
+  }
+}
+
+/**
+ * Save a compiled Rune to disk, with a JSON manifest next to it so it can be
+ * imported somewhere else.
+ */
+export async function export_rune(
+  path: string,
+  rune: string,
+  pipeline: Pipeline,
+  columnMappings: ColumnMapping[],
+  inputSchema: Schema
+): Promise<Result<RuneManifest>> {
+  try {
+    const response = await invoke("export_rune", {
+      path,
+      rune,
+      pipeline,
+      columnMappings,
+      inputSchema,
+    });
+    return ok(response as RuneManifest);
+  } catch (e) {
+    return err(is_serializable_error(e) ? e : to_serializable_error(e));
+  }
+}
+
+/**
+ * Import a Rune that was saved using `export_rune()` into a project.
+ *
+ * This will fail if the Rune uses proc-blocks that haven't been installed.
+ */
+export async function import_rune(
+  path: string,
+  project: string
+): Promise<Result<ImportedRune, SerializableError<MissingProcBlocks>>> {
+  try {
+    const response = await invoke("import_rune", { path, project });
+    return ok(response as ImportedRune);
+  } catch (e) {
+    return err(is_serializable_error(e) ? e : to_serializable_error(e));
+  }
+}
+
+/**
+ * Load a compiled Rune so it can be run multiple times without needing to be
+ * re-initialized, returning the session's ID.
+ *
+ * Sessions that sit idle for a while will be unloaded automatically.
+ *
+ * @param runId An ID that can be passed to `cancel_analysis()` to stop
+ * loading. One will be generated if it isn't provided.
+ */
+export async function load_rune(
+  rune: string,
+  runId?: string
+): Promise<Result<string, SerializableError<RunFailed>>> {
+  try {
+    const response = await invoke("load_rune", { rune, runId: runId ?? null });
+    return ok(response as string);
+  } catch (e) {
+    return err(is_serializable_error(e) ? e : to_serializable_error(e));
+  }
+}
+
+/**
+ * Run a Rune that was loaded using `load_rune()`.
+ *
+ * @param inputs The tensor to use for each node's inputs.
+ * @param debug Save every intermediate tensor so it can be inspected using
+ * `get_debug_run()`. The run's ID is in `debug_run`.
+ * @param runId An ID that can be passed to `cancel_analysis()` to stop the
+ * run. One will be generated if it isn't provided.
+ */
+export async function run_rune(
+  sessionId: string,
+  inputs: InputBinding[],
+  debug?: boolean,
+  runId?: string
+): Promise<Result<RuneOutputs, SerializableError<RunFailed>>> {
+  try {
+    const response = await invoke("run_rune", {
+      sessionId,
+      inputs,
+      debug,
+      runId: runId ?? null,
+    });
+    return ok(response as RuneOutputs);
+  } catch (e) {
+    return err(is_serializable_error(e) ? e : to_serializable_error(e));
+  }
+}
+
+/**
+ * Unload a Rune that was loaded using `load_rune()`.
+ */
+export async function unload_rune(sessionId: string): Promise<boolean> {
+  return await invoke("unload_rune", { sessionId });
+}
+
+/**
+ * Get the list of tensors that were captured during a debug run.
+ */
+export async function get_debug_run(
+  runId: string
+): Promise<Result<DebugRun>> {
+  try {
+    const response = await invoke("get_debug_run", { runId });
+    return ok(response as DebugRun);
+  } catch (e) {
+    return err(is_serializable_error(e) ? e : to_serializable_error(e));
+  }
+}
+
+/**
+ * Read a tensor that was captured during a debug run.
+ *
+ * @param name The tensor's name.
+ */
+export async function read_captured_tensor(
+  runId: string,
+  node: string,
+  direction: TensorDirection,
+  name: string
+): Promise<Result<Table>> {
+  try {
+    const blob: string = await invoke("read_captured_tensor", {
+      runId,
+      node,
+      direction,
+      name,
+    });
+    const bytes = await fetch_blob(blob);
+    await release_blob(blob);
+
+    return ok(tableFromIPC(bytes));
+  } catch (e) {
+    return err(is_serializable_error(e) ? e : to_serializable_error(e));
+  }
+}
+
+/**
+ * Delete the tensors captured during a debug run.
+ */
+export async function delete_debug_run(
+  runId: string
+): Promise<Result<undefined>> {
+  try {
+    await invoke("delete_debug_run", { runId });
+    return ok(undefined);
+  } catch (e) {
+    return err(is_serializable_error(e) ? e : to_serializable_error(e));
+  }
+}
+
+/**
+ * Get the time and memory limits applied when running a Rune.
+ */
+export async function get_run_limits(): Promise<RunLimits> {
+  return await invoke("get_run_limits");
+}
+
+/**
+ * Change the time and memory limits applied when running a Rune.
+ *
+ * Runs that take too long fail with a `timeout` error, and Runes containing
+ * modules that need more memory than allowed fail with `resource_exhausted`.
+ */
+export async function set_run_limits(runLimits: RunLimits): Promise<RunLimits> {
+  return await invoke("set_run_limits", { runLimits });
+}
+
+/**
+ * Run a Rune over every row in a table.
+ *
+ * Progress is reported using `dataset_run_progress` events, the last of which
+ * will have a `completed`, `cancelled`, or `failed` state.
+ *
+ * @param columnMappings Which column to feed into each node's input. The
+ * column's name goes in `tensor_id`.
+ * @param outputTable A table to save the outputs to. Each output has a
+ * `row_id` column which can be used to join it back onto the input table.
+ * @param runId An ID that can be passed to `cancel_analysis()` to stop the
+ * run. One will be generated if it isn't provided.
+ */
+export async function run_over_dataset(
+  rune: string,
+  table: string,
+  columnMappings: ColumnMapping[],
+  batchSize: number,
+  outputTable?: string,
+  runId?: string
+): Promise<Result<DatasetRunSummary, SerializableError<RunFailed>>> {
+  try {
+    const response = await invoke("run_over_dataset", {
+      rune,
+      table,
+      columnMappings,
+      batchSize,
+      outputTable: outputTable ?? null,
+      runId: runId ?? null,
+    });
+    return ok(response as DatasetRunSummary);
+  } catch (e) {
+    return err(is_serializable_error(e) ? e : to_serializable_error(e));
+  }
+}
+
+/**
+ * Stop a running analysis, returning `true` if it was still running.
+ *
+ * Cancelled runs fail with a `cancelled` error.
+ */
+export async function cancel_analysis(runId: string): Promise<boolean> {
+  return await invoke("cancel_analysis", { runId });
+}
+
+/**
+ * Get the settings for a project, using the defaults if they haven't been
+ * saved yet.
+ */
+export async function get_project_settings(
+  name: string
+): Promise<Result<ProjectSettings>> {
+  try {
+    const response = await invoke("get_project_settings", { name });
+    return ok(response as ProjectSettings);
+  } catch (e) {
+    return err(is_serializable_error(e) ? e : to_serializable_error(e));
+  }
+}
+
+export async function save_project_settings(
+  name: string,
+  settings: ProjectSettings
+): Promise<Result<undefined>> {
+  try {
+    await invoke("save_project_settings", { name, settings });
+    return ok(undefined);
+  } catch (e) {
+    return err(is_serializable_error(e) ? e : to_serializable_error(e));
+  }
+}
+
+/**
+ * Save the pipeline a project is working on.
+ */
+export async function save_pipeline(
+  project: string,
+  pipeline: Pipeline
+): Promise<Result<undefined>> {
+  try {
+    await invoke("save_pipeline", { project, pipeline });
+    return ok(undefined);
+  } catch (e) {
+    return err(is_serializable_error(e) ? e : to_serializable_error(e));
+  }
+}
+
+/**
+ * Get the pipeline that was saved for a project, if there is one.
+ */
+export async function get_pipeline(
+  project: string
+): Promise<Result<Pipeline | null>> {
+  try {
+    const response = await invoke("get_pipeline", { project });
+    return ok(response as Pipeline | null);
+  } catch (e) {
+    return err(is_serializable_error(e) ? e : to_serializable_error(e));
+  }
+}
+
+/**
+ * Delete every previously compiled Rune from the on-disk cache.
+ */
+export async function clear_compilation_cache(): Promise<Result<ClearedCache>> {
+  try {
+    const response = await invoke("clear_compilation_cache");
+    return ok(response as ClearedCache);
+  } catch (e) {
+    return err(is_serializable_error(e) ? e : to_serializable_error(e));
+  }
+}
+
+/**
+ * Get the URL a blob in the backend's blob store can be fetched from.
+ *
+ * Large payloads (compiled Runes, Arrow tables, tensors, etc.) are kept in
+ * the backend and referenced by ID because sending them through `invoke()`
+ * is really slow.
+ */
+export function blob_url(id?: string): string {
+  // Windows serves custom protocols from a different URL
+  const base = navigator.userAgent.includes("Windows")
+    ? "https://weld.localhost/blobs"
+    : "weld://localhost/blobs";
+
+  return id ? `${base}/${id}` : base;
+}
+
+/**
+ * Read the raw bytes for a blob.
+ */
+export async function fetch_blob(id: string): Promise<Uint8Array> {
+  const response = await fetch(blob_url(id));
+
+  if (!response.ok) {
+    throw new Error(`Unable to fetch blob ${id}: ${response.status}`);
+  }
+
+  return new Uint8Array(await response.arrayBuffer());
+}
+
+/**
+ * Upload some bytes to the blob store so they can be passed to a command by
+ * ID.
+ */
+export async function upload_blob(
+  bytes: Uint8Array,
+  contentType: string = "application/octet-stream"
+): Promise<string> {
+  const response = await fetch(blob_url(), {
+    method: "POST",
+    headers: { "Content-Type": contentType },
+    body: bytes,
+  });
+
+  if (!response.ok) {
+    throw new Error(`Unable to upload a blob: ${response.status}`);
+  }
+
+  return await response.text();
+}
+
+/**
+ * Tell the backend a blob is no longer needed.
+ */
+export async function release_blob(id: string): Promise<boolean> {
+  return await invoke("release_blob", { id });
+}
+
 export function is_serializable_error(
   value: any
 ): value is SerializableError<any> {
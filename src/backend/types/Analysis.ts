@@ -1,3 +1,4 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { RunProfile } from "./RunProfile";
 
-export interface Analysis { run_time_ms: number, }
\ No newline at end of file
+export interface Analysis { run_time_ms: number, profile: RunProfile, }
\ No newline at end of file
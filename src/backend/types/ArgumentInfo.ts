@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ArgumentType } from "./ArgumentType";
+
+export interface ArgumentInfo { name: string, description: string | null, type: ArgumentType, default_value: string | null, }
\ No newline at end of file
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type ArgumentType = { type: "integer" } | { type: "unsigned_integer" } | { type: "float" } | { type: "string" } | { type: "long_string" } | { type: "one_of", value: Array<string> } | { type: "unknown" };
\ No newline at end of file
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ElementType } from "./ElementType";
+import type { TensorDirection } from "./TensorDirection";
+
+export interface CapturedTensor { node: string, direction: TensorDirection, name: string, element_type: ElementType, dimensions: Array<number>, file: string, }
\ No newline at end of file
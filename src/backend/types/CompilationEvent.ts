@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CompilationEventKind } from "./CompilationEventKind";
+
+export interface CompilationEvent { elapsed_ms: number, kind: CompilationEventKind, }
\ No newline at end of file
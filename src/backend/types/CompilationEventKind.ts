@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CompilationPhase } from "./CompilationPhase";
+import type { CompilerCacheStats } from "./CompilerCacheStats";
+import type { Diagnostic } from "./Diagnostic";
+
+export type CompilationEventKind = { type: "phase_started", value: { phase: CompilationPhase, } } | { type: "phase_finished", value: { phase: CompilationPhase, duration_ms: number, } } | { type: "asset_started", value: { uri: string, stage: string | null, } } | { type: "asset_finished", value: { uri: string, stage: string | null, bytes: number, } } | { type: "asset_failed", value: { uri: string, stage: string | null, error: string, } } | { type: "succeeded", value: { cache_stats: CompilerCacheStats, } } | { type: "failed", value: { error: string, diagnostics: Array<Diagnostic>, } };
\ No newline at end of file
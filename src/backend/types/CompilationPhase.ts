@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type CompilationPhase = "parsing" | "resolving_assets" | "type_checking" | "codegen" | "archiving";
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CompilerCacheStats } from "./CompilerCacheStats";
+
+export interface CompileResponse { rune: string, cache_stats: CompilerCacheStats, }
\ No newline at end of file
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface CompilerCacheStats { recomputed: number, reused: number, archive_cache_hit: boolean, }
\ No newline at end of file
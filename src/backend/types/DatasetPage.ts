@@ -1,3 +1,3 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
-export interface DatasetPage { total_records: number, table: Array<number>, }
\ No newline at end of file
+export interface DatasetPage { total_records: number, table: string, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { RunState } from "./RunState";
+
+export interface DatasetRunProgress { run_id: string, state: RunState, rows_processed: number, total_rows: number, batches: number, elapsed_ms: number, rows_per_second: number, eta_ms: number | null, }
\ No newline at end of file
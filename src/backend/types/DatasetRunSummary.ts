@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { RunProfile } from "./RunProfile";
+
+export interface DatasetRunSummary { run_id: string, rows_processed: number, batches: number, elapsed_ms: number, output_table: string | null, skipped_outputs: Array<string>, profile: RunProfile, }
\ No newline at end of file
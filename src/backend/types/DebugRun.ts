@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CapturedTensor } from "./CapturedTensor";
+
+export interface DebugRun { run_id: string, tensors: Array<CapturedTensor>, }
\ No newline at end of file
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Severity } from "./Severity";
+import type { Span } from "./Span";
+
+export interface Diagnostic { severity: Severity, code: string, message: string, stage: string | null, argument: string | null, tensor: string | null, span: Span | null, }
\ No newline at end of file
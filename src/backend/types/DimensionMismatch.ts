@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type DimensionMismatch = { type: "rank", value: { expected: number, actual: number, } } | { type: "length", value: { dimension: number, expected: number, actual: number, } };
\ No newline at end of file
@@ -1,3 +1,3 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
-export type Dimensions = "Dynamic" | { Fixed: Array<number> };
\ No newline at end of file
+export type Dimensions = { type: "dynamic" } | { type: "fixed", value: Array<number | null> };
\ No newline at end of file
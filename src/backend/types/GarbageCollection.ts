@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ProcBlockDiskUsage } from "./ProcBlockDiskUsage";
+
+export interface GarbageCollection { removed: Array<ProcBlockDiskUsage>, bytes_freed: bigint, dry_run: boolean, }
\ No newline at end of file
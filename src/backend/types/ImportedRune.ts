@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { RuneManifest } from "./RuneManifest";
+
+export interface ImportedRune { path: string, rune: string, manifest: RuneManifest, }
\ No newline at end of file
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DimensionMismatch } from "./DimensionMismatch";
+import type { Dimensions } from "./Dimensions";
+import type { ElementType } from "./ElementType";
+
+export type Incompatibility = { type: "element_type", value: { expected: Array<ElementType>, actual: ElementType, } } | { type: "dimensions", value: { expected: Dimensions, actual: Dimensions, reason: DimensionMismatch, } };
\ No newline at end of file
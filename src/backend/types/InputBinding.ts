@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Tensor } from "./Tensor";
+
+export interface InputBinding { node_name: string, input_tensor_name: string, tensor: Tensor, }
\ No newline at end of file
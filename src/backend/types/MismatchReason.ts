@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DimensionMismatch } from "./DimensionMismatch";
+import type { Dimensions } from "./Dimensions";
+import type { ElementType } from "./ElementType";
+
+export type MismatchReason = { type: "unknown_node" } | { type: "unknown_tensor", value: { available: Array<string>, } } | { type: "duplicate_binding" } | { type: "element_type", value: { expected: ElementType, actual: ElementType, } } | { type: "dimensions", value: { expected: Dimensions, actual: Array<number>, reason: DimensionMismatch, } } | { type: "buffer_length", value: { expected: number, actual: number, } };
\ No newline at end of file
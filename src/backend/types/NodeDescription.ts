@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { RuneNodeKind } from "./RuneNodeKind";
+import type { TensorDescription } from "./TensorDescription";
+
+export interface NodeDescription { name: string, kind: RuneNodeKind, identifier: string | null, is_input: boolean, is_output: boolean, inputs: Array<TensorDescription>, outputs: Array<TensorDescription>, }
\ No newline at end of file
@@ -1,3 +1,3 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
-export interface Package { name: string, description: string, lastVersion: string, publicUrl: string, }
\ No newline at end of file
+export interface Package { name: string, description: string, lastVersion: string, publicUrl: string, local: boolean, }
\ No newline at end of file
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ArgumentInfo } from "./ArgumentInfo";
+import type { TensorSignature } from "./TensorSignature";
+
+export interface ProcBlockSignature { name: string, version: string, description: string | null, arguments: Array<ArgumentInfo>, inputs: Array<TensorSignature>, outputs: Array<TensorSignature>, }
\ No newline at end of file
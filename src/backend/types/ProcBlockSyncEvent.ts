@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Package } from "./Package";
+import type { SyncSummary } from "./SyncSummary";
+
+export type ProcBlockSyncEvent = { type: "started", value: { packages: Array<Package>, } } | { type: "progress", value: { name: string, version: string, downloaded_bytes: bigint, total_bytes: bigint | null, } } | { type: "finished", value: { name: string, version: string, } } | { type: "failed", value: { name: string, version: string, error: string, } } | { type: "completed", value: SyncSummary };
\ No newline at end of file
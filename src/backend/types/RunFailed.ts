@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { InputMismatch } from "./InputMismatch";
+
+export type RunFailed = { type: "session_not_found", value: { session_id: string, } } | { type: "invalid_inputs", value: { mismatches: Array<InputMismatch>, } } | { type: "timeout", value: { limit_ms: number, } } | { type: "resource_exhausted", value: { module: string, required_bytes: number, limit_bytes: number, } } | { type: "cancelled", value: { run_id: string, } } | { type: "not_implemented" };
\ No newline at end of file
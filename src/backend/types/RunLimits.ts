@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface RunLimits { timeout_ms: number | null, max_memory_bytes: number | null, }
\ No newline at end of file
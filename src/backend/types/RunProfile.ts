@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { NodeProfile } from "./NodeProfile";
+
+export interface RunProfile { elapsed_us: number, nodes: Record<string, NodeProfile>, }
\ No newline at end of file
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ArchiveFile } from "./ArchiveFile";
+import type { NodeDescription } from "./NodeDescription";
+import type { ResourceDescription } from "./ResourceDescription";
+
+export interface RuneDescription { runefile: string | null, nodes: Array<NodeDescription>, resources: Array<ResourceDescription>, files: Array<ArchiveFile>, }
\ No newline at end of file
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ColumnMapping } from "./ColumnMapping";
+import type { Pipeline } from "./Pipeline";
+import type { ProcBlockVersion } from "./ProcBlockVersion";
+import type { Schema } from "./Schema";
+
+export interface RuneManifest { manifest_version: number, created_at: string, pipeline: Pipeline, proc_blocks: Array<ProcBlockVersion>, column_mappings: Array<ColumnMapping>, input_schema: Schema, }
\ No newline at end of file
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { RunProfile } from "./RunProfile";
+import type { Tensor } from "./Tensor";
+
+export interface RuneOutputs { nodes: Record<string, Record<string, Tensor>>, profile: RunProfile, debug_run: string | null, }
\ No newline at end of file
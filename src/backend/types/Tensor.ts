@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ElementType } from "./ElementType";
+
+export interface Tensor { element_type: ElementType, dimensions: Array<number>, buffer: Array<number>, }
\ No newline at end of file
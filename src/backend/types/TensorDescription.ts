@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ElementType } from "./ElementType";
+
+export interface TensorDescription { name: string, element_type: ElementType | null, dimensions: Array<number>, }
\ No newline at end of file
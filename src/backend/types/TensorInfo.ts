@@ -1,4 +1,5 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Dimensions } from "./Dimensions";
 import type { ElementType } from "./ElementType";
 
-export interface TensorInfo { id: string, display_name: string, dimensions: Array<number>, element_type: ElementType, }
\ No newline at end of file
+export interface TensorInfo { id: string, display_name: string, dimensions: Dimensions, element_type: ElementType, }
\ No newline at end of file
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Dimensions } from "./Dimensions";
+import type { ElementType } from "./ElementType";
+
+export interface TensorSignature { name: string, description: string | null, element_types: Array<ElementType>, dimensions: Dimensions, }
\ No newline at end of file
@@ -1,3 +1,3 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
-export interface ValidationResponse { row_count: number, preview: Array<number>, }
\ No newline at end of file
+export interface ValidationResponse { row_count: number, preview: string, }
\ No newline at end of file
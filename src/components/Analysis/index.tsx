@@ -27,6 +27,9 @@ import {
 } from "src/assets";
 import { generateNodeKey, sanitizeName, storm2rune } from "src/canvas2rune";
 import { diagramToRuneCanvas } from "./utils/FlowUtils";
+import { RuneOutputs } from "src/backend/types/RuneOutputs";
+import { RuneDescription } from "src/backend/types/RuneDescription";
+import { InputBinding } from "src/backend/types/InputBinding";
 import { Console } from "console-feed";
 import React from "react";
 import ArrowTable, {
@@ -44,6 +47,7 @@ function Analysis({
   isLoadingTable,
   setLogs,
   logs,
+  projectName,
 }: {
   data: any[];
   querySchema: any;
@@ -54,6 +58,7 @@ function Analysis({
   isLoadingTable: boolean;
   setLogs: (error: any) => void;
   logs: any[];
+  projectName: string;
 }) {
   const diagram = useAppSelector((s) => s.flow);
   const components = useAppSelector((s) => s.builder.components);
@@ -303,32 +308,53 @@ function Analysis({
     setResultData([]);
     try {
       console.log("Runefile", rune);
-      const zune = await invoke("compile", { runefile: rune });
-      console.log("ZUNE BUILT");
+      const { rune: zune, cache_stats }: any = await invoke("compile", {
+        runefile: rune,
+        project: projectName,
+      });
+      console.log("ZUNE BUILT", cache_stats);
       try {
-        result = await invoke("reune", {
+        // Each dataset is fed into its capability node's only input tensor
+        const description = (await invoke("inspect_rune", {
+          rune: zune,
+        })) as RuneDescription;
+        const bindings: InputBinding[] = Object.entries(input_tensors).map(
+          ([nodeName, tensor]: [string, any]) => {
+            const node = description.nodes.find((n) => n.name === nodeName);
+            return {
+              node_name: nodeName,
+              input_tensor_name: node?.inputs[0]?.name ?? "",
+              tensor,
+            };
+          }
+        );
+        result = (await invoke("reune", {
           zune: zune,
-          inputTensors: input_tensors,
-        });
-        Object.entries(result).forEach(([rkey, value]: [string, any]) => {
-          const keys = rkey.split("_");
-          let key: string = rkey;
-          if (keys && keys.length && keys.length > 1) key = keys[1];
-          if (keys[1] === "block" && keys[0] === "proc" && keys.length > 2)
-            key = keys[2];
-          const resultSet = result[rkey];
-          const tensorResult = convertTensorResult(resultSet);
-          const Result = transformByDimensions(
-            resultSet.dimensions,
-            tensorResult
-          );
-          Result.forEach((row, index) => {
-            let tup = {};
-            tup[key] = Result[index] !== undefined ? Result[index] : "";
-            if (resultTable[index])
-              resultTable[index][key] =
-                Result[index] !== undefined ? Result[index] : "";
-            else resultTable[index] = tup;
+          inputTensors: bindings,
+        })) as RuneOutputs;
+        const outputNodes = Object.entries(result.nodes);
+        outputNodes.forEach(([nodeName, tensors]) => {
+          Object.entries(tensors).forEach(([rkey, resultSet]) => {
+            const keys = rkey.split("_");
+            let key: string = rkey;
+            if (keys && keys.length && keys.length > 1) key = keys[1];
+            if (keys[1] === "block" && keys[0] === "proc" && keys.length > 2)
+              key = keys[2];
+            // Avoid column name collisions when there are several outputs
+            if (outputNodes.length > 1) key = `${nodeName}.${key}`;
+            const tensorResult = convertTensorResult(resultSet);
+            const Result = transformByDimensions(
+              resultSet.dimensions,
+              tensorResult
+            );
+            Result.forEach((row, index) => {
+              let tup = {};
+              tup[key] = Result[index] !== undefined ? Result[index] : "";
+              if (resultTable[index])
+                resultTable[index][key] =
+                  Result[index] !== undefined ? Result[index] : "";
+              else resultTable[index] = tup;
+            });
           });
         });
         setResultData(resultTable);
@@ -337,7 +363,7 @@ function Analysis({
         );
       } catch (error) {
         console.log("RUN ERROR", error, result);
-        setLogs(error.backtrace);
+        setLogs(error.verbose);
       }
     } catch (error) {
       console.log("COMPILE ERROR", error);
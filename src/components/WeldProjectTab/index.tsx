@@ -376,6 +376,7 @@ class WeldProjectTab extends React.Component<WeldProject, WeldProject> {
                 path="/analysis/:id"
                 element={
                   <Anaysis
+                    projectName={this.state.name ?? this.state.id}
                     logs={logs}
                     setLogs={(error) => {
                       this.setState({